@@ -0,0 +1,711 @@
+use crate::filesys::{
+    Payload, FILENAME_APPS_BLACKLIST, FILENAME_AUTO_REPLACEMENT, FILENAME_FOLDERS,
+    FILENAME_KEYBOARD_LAYOUTS, FILENAME_SETTINGS, FOLDER_DATA,
+};
+use crate::helpers::{app_data_dir, get_tauri_handle, get_timestamp_millis};
+use crate::history::{content_hash, folder_path, get_history_instance, save_history, HistoryItem};
+use crate::{auto_replacement, crypto, folders, keyboard_layouts, processes, settings, thumbnails};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const ARCHIVE_VERSION: u32 = 1;
+const FILENAME_MANIFEST: &str = "manifest.json";
+const FOLDER_CONFIG: &str = "config";
+/// Next to the data dir, so moving files between them is a rename
+const FOLDER_IMPORT: &str = "import";
+/// Items extracted from the archive, moved into the data dir once all of them are read
+const FOLDER_STAGED: &str = "staged";
+/// Current items moved out of the way while the staged ones are moved in
+const FOLDER_PREVIOUS: &str = "previous";
+
+/// Config files moved between machines, `encryption.json` isn't: the key never leaves the machine
+const CONFIG_FILES: [&str; 5] = [
+    FILENAME_AUTO_REPLACEMENT,
+    FILENAME_APPS_BLACKLIST,
+    FILENAME_SETTINGS,
+    FILENAME_KEYBOARD_LAYOUTS,
+    FILENAME_FOLDERS,
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// ms since unix epoch
+    exported_at: u64,
+    items: Vec<HistoryItem>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// keeps current items and config, adds new items from archive
+    #[default]
+    Merge,
+    /// removes current items, overwrites config
+    Replace,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ExportReport {
+    pub items: usize,
+    /// `folder/name` of items whose files couldn't be read
+    pub skipped: Vec<String>,
+    pub configs: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// `folder/name` of archive items whose contents are already in history
+    pub duplicates: Vec<String>,
+    /// `folder/name -> folder/new_name` of archive items renamed because the name was taken
+    pub conflicts: Vec<String>,
+    /// `folder/name` of archive items which couldn't be imported
+    pub skipped: Vec<String>,
+    pub configs: Vec<String>,
+    /// config files kept as they are in merge mode
+    pub kept_configs: Vec<String>,
+}
+
+fn data_dir() -> PathBuf {
+    app_data_dir().join(FOLDER_DATA)
+}
+
+/// Archive entry of a stored file, `data/<folder>/<file>`
+fn entry_name(folder: &str, file: &Path) -> String {
+    let file = file
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    [FOLDER_DATA, folder, &file].join("/")
+}
+
+/// Folder and file names from an archive must not point outside of the data dir
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && Path::new(name).file_name().is_some_and(|n| n == name)
+}
+
+/// Writes history items, their metadata and config files into a zip archive.
+/// Encrypted items are stored decrypted, so the archive can be imported on another machine
+#[tauri::command]
+pub async fn export_data(path: String) -> Result<String, String> {
+    let report = export_archive(Path::new(&path))?;
+
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+fn export_archive(path: &Path) -> Result<ExportReport, String> {
+    if crypto::is_enabled() && !crypto::is_unlocked() {
+        return Err("History is locked".to_string());
+    }
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut report = ExportReport::default();
+    let mut items = Vec::new();
+
+    // a copy, capture isn't blocked while the archive is written
+    let history_items = get_history_instance().lock().items();
+
+    for item in history_items {
+        let contents: Result<Vec<(String, Vec<u8>)>, String> = item
+            .files()
+            .iter()
+            .map(|file| Ok((entry_name(&item.folder, file), crypto::read_file(file)?)))
+            .collect();
+
+        let Ok(contents) = contents else {
+            report
+                .skipped
+                .push(format!("{}/{}", item.folder, item.name));
+            continue;
+        };
+
+        for (name, data) in contents {
+            zip.start_file(name, options).map_err(|e| e.to_string())?;
+            zip.write_all(&data).map_err(|e| e.to_string())?;
+        }
+
+        let mut item = item;
+        item.encrypted = false;
+        items.push(item);
+    }
+
+    for config in CONFIG_FILES {
+        let Ok(data) = fs::read(data_dir().join(config)) else {
+            continue;
+        };
+
+        zip.start_file([FOLDER_CONFIG, config].join("/"), options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(&data).map_err(|e| e.to_string())?;
+        report.configs.push(config.to_string());
+    }
+
+    report.items = items.len();
+    let manifest = Manifest {
+        version: ARCHIVE_VERSION,
+        exported_at: get_timestamp_millis(),
+        items,
+    };
+
+    zip.start_file(FILENAME_MANIFEST, options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?)
+        .map_err(|e| e.to_string())?;
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(report)
+}
+
+/// Reads an archive made by `export_data`, reports duplicates and renamed items
+#[tauri::command]
+pub async fn import_data(path: String, mode: Option<ImportMode>) -> Result<String, String> {
+    let report = import_archive(Path::new(&path), mode.unwrap_or_default())?;
+
+    get_tauri_handle()
+        .emit_all(
+            "clipboard",
+            Payload {
+                message: String::from("contents"),
+            },
+        )
+        .unwrap();
+
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
+
+fn import_archive(path: &Path, mode: ImportMode) -> Result<ImportReport, String> {
+    if crypto::is_enabled() && !crypto::is_unlocked() {
+        return Err("History is locked".to_string());
+    }
+
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut zip = ZipArchive::new(file).map_err(|e| e.to_string())?;
+
+    let manifest: Manifest = serde_json::from_slice(&read_entry(&mut zip, FILENAME_MANIFEST)?)
+        .map_err(|e| e.to_string())?;
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(format!("Unsupported archive version {}", manifest.version));
+    }
+
+    // broken config files are left out before anything is changed
+    let configs: Vec<(&str, Vec<u8>)> = CONFIG_FILES
+        .into_iter()
+        .filter_map(|config| {
+            let data = read_entry(&mut zip, &[FOLDER_CONFIG, config].join("/")).ok()?;
+            serde_json::from_slice::<serde_json::Value>(&data).ok()?;
+            Some((config, data))
+        })
+        .collect();
+
+    let mut report = match mode {
+        ImportMode::Merge => merge_items(&mut zip, manifest.items),
+        ImportMode::Replace => replace_items(&mut zip, manifest.items)?,
+    };
+
+    for (config, data) in configs {
+        let target = data_dir().join(config);
+        if mode == ImportMode::Merge && target.is_file() {
+            report.kept_configs.push(config.to_string());
+            continue;
+        }
+
+        fs::write(target, data).map_err(|e| e.to_string())?;
+        report.configs.push(config.to_string());
+    }
+
+    reload_configs(&report.configs);
+
+    Ok(report)
+}
+
+/// Adds archive items next to the current ones, an item which can't be read is skipped
+fn merge_items(zip: &mut ZipArchive<File>, items: Vec<HistoryItem>) -> ImportReport {
+    let mut report = ImportReport::default();
+
+    for item in items {
+        let folder = item.folder.clone();
+        let id = format!("{}/{}", folder, item.name);
+        if !is_plain_name(&item.folder) || !is_plain_name(&item.name) {
+            report.skipped.push(id);
+            continue;
+        }
+
+        match import_item(zip, item) {
+            Ok(ImportedItem::New) => report.imported += 1,
+            Ok(ImportedItem::Renamed(new_name)) => {
+                report.imported += 1;
+                report
+                    .conflicts
+                    .push(format!("{} -> {}/{}", id, folder, new_name));
+            }
+            Ok(ImportedItem::Duplicate) => report.duplicates.push(id),
+            Err(e) => {
+                eprintln!("Couldn't import {}: {}", id, e);
+                report.skipped.push(id);
+            }
+        }
+    }
+
+    report
+}
+
+/// Replaces current items with archive items. The archive is extracted into a staging dir first,
+/// current items are removed only when every item was read and the staged ones are in place
+fn replace_items(
+    zip: &mut ZipArchive<File>,
+    items: Vec<HistoryItem>,
+) -> Result<ImportReport, String> {
+    let staging = app_data_dir().join(FOLDER_IMPORT);
+    let staged_dir = staging.join(FOLDER_STAGED);
+    // left by an import which didn't finish
+    let _ = fs::remove_dir_all(&staged_dir);
+
+    let result = stage_items(zip, items, &staged_dir)
+        .and_then(|(report, staged)| swap_in(&staging, staged).map(|_| report));
+
+    match result {
+        Ok(_) => {
+            if let Err(e) = fs::remove_dir_all(&staging) {
+                eprintln!("Error removing {:?}: {}", staging, e);
+            }
+            thumbnails::remove_all();
+        }
+        // current files which couldn't be moved back stay in the previous dir
+        Err(_) => {
+            let _ = fs::remove_dir_all(&staged_dir);
+        }
+    }
+
+    result
+}
+
+/// Writes item files into `dir/<folder>/`, fails on the first item which can't be read
+fn stage_items(
+    zip: &mut ZipArchive<File>,
+    items: Vec<HistoryItem>,
+    dir: &Path,
+) -> Result<(ImportReport, Vec<HistoryItem>), String> {
+    let mut report = ImportReport::default();
+    let mut staged: Vec<HistoryItem> = Vec::new();
+
+    for item in items {
+        let id = format!("{}/{}", item.folder, item.name);
+        let taken = staged
+            .iter()
+            .any(|s| s.folder == item.folder && s.name == item.name);
+        if !is_plain_name(&item.folder) || !is_plain_name(&item.name) || taken {
+            report.skipped.push(id);
+            continue;
+        }
+
+        let files = read_item_files(zip, &item)
+            .map_err(|e| format!("Couldn't read {}, nothing was replaced: {}", id, e))?;
+
+        let bytes: Vec<u8> = files.iter().flat_map(|(_, b)| b.iter().copied()).collect();
+        let hash = content_hash(&bytes);
+        if staged.iter().any(|s| s.hash == hash) {
+            report.duplicates.push(id);
+            continue;
+        }
+
+        let folder = dir.join(&item.folder);
+        fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+        for (file, (_, data)) in item.files().iter().zip(files.iter()) {
+            let name = file.file_name().unwrap_or_default();
+            crypto::write_file(&folder.join(name), data)?;
+        }
+
+        let mut item = item;
+        item.hash = hash;
+        item.size = bytes.len() as u64;
+        item.encrypted = crypto::is_enabled();
+        staged.push(item);
+        report.imported += 1;
+    }
+
+    Ok((report, staged))
+}
+
+/// Moves staged items into the data dir in place of the current ones, capture waits meanwhile.
+/// Nothing changes when a file can't be moved
+fn swap_in(staging: &Path, items: Vec<HistoryItem>) -> Result<(), String> {
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    let previous_dir = staging.join(FOLDER_PREVIOUS);
+    let previous: Vec<(PathBuf, PathBuf)> = history
+        .items()
+        .iter()
+        .flat_map(|item| {
+            let folder = previous_dir.join(&item.folder);
+            item.files()
+                .into_iter()
+                .filter(|file| file.exists())
+                .map(move |file| {
+                    let to = folder.join(file.file_name().unwrap_or_default());
+                    (file, to)
+                })
+        })
+        .collect();
+
+    let staged_dir = staging.join(FOLDER_STAGED);
+    let staged: Vec<(PathBuf, PathBuf)> = items
+        .iter()
+        .flat_map(|item| {
+            let folder = staged_dir.join(&item.folder);
+            item.files().into_iter().map(move |file| {
+                let from = folder.join(file.file_name().unwrap_or_default());
+                (from, file)
+            })
+        })
+        .collect();
+
+    move_all(&previous)?;
+    if let Err(e) = move_all(&staged) {
+        move_back(&previous);
+        return Err(e);
+    }
+
+    history.replace_items(items);
+    save_history(&history);
+
+    Ok(())
+}
+
+/// Renames files `from -> to`, the ones already renamed are moved back when one of them fails
+fn move_all(moves: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+    for (i, (from, to)) in moves.iter().enumerate() {
+        let moved = to
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::rename(from, to));
+
+        if let Err(e) = moved {
+            move_back(&moves[..i]);
+            return Err(format!("Couldn't move {:?}: {}", from, e));
+        }
+    }
+
+    Ok(())
+}
+
+fn move_back(moves: &[(PathBuf, PathBuf)]) {
+    for (from, to) in moves.iter().rev() {
+        if let Err(e) = fs::rename(to, from) {
+            eprintln!("Error moving {:?} back to {:?}: {}", to, from, e);
+        }
+    }
+}
+
+enum ImportedItem {
+    New,
+    Renamed(String),
+    Duplicate,
+}
+
+/// Contents of item files by their extension, in the order of `HistoryItem::files`
+fn read_item_files(
+    zip: &mut ZipArchive<File>,
+    item: &HistoryItem,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut files = Vec::new();
+    for file in item.files() {
+        let data = read_entry(zip, &entry_name(&item.folder, &file))?;
+        let extension = file
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default();
+        files.push((extension, data));
+    }
+
+    Ok(files)
+}
+
+fn import_item(zip: &mut ZipArchive<File>, item: HistoryItem) -> Result<ImportedItem, String> {
+    let files = read_item_files(zip, &item)?;
+
+    let bytes: Vec<u8> = files.iter().flat_map(|(_, b)| b.iter().copied()).collect();
+    let hash = content_hash(&bytes);
+
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    if history.find_by_hash_mut(&hash).next().is_some() {
+        return Ok(ImportedItem::Duplicate);
+    }
+
+    let folder = folder_path(&item.folder);
+    fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+
+    // a taken name gets a fresh timestamp, like a new capture would
+    let mut stem = item
+        .path()
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let renamed =
+        history.find(&item.folder, &item.name).is_some() || folder.join(&item.name).exists();
+    if renamed {
+        let mut timestamp = get_timestamp_millis();
+        while files
+            .iter()
+            .any(|(extension, _)| folder.join(format!("{}.{}", timestamp, extension)).exists())
+        {
+            timestamp += 1;
+        }
+        stem = timestamp.to_string();
+    }
+
+    for (extension, data) in files.iter() {
+        crypto::write_file(&folder.join(format!("{}.{}", stem, extension)), data)?;
+    }
+
+    let mut imported = item.clone();
+    imported.name = format!("{}.{}", stem, item.extension());
+    imported.hash = hash;
+    imported.size = bytes.len() as u64;
+    imported.encrypted = crypto::is_enabled();
+
+    let name = imported.name.clone();
+    history.add(imported);
+    save_history(&history);
+
+    if renamed {
+        Ok(ImportedItem::Renamed(name))
+    } else {
+        Ok(ImportedItem::New)
+    }
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = zip.by_name(name).map_err(|e| e.to_string())?;
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data).map_err(|e| e.to_string())?;
+
+    Ok(data)
+}
+
+fn reload_configs(configs: &[String]) {
+    for config in configs {
+        let result = match config.as_str() {
+            FILENAME_SETTINGS => settings::update_settings(),
+            FILENAME_AUTO_REPLACEMENT => auto_replacement::update_auto_replace_data(),
+            FILENAME_APPS_BLACKLIST => processes::update_blacklist_data(),
+            FILENAME_KEYBOARD_LAYOUTS => keyboard_layouts::update_keyboard_layouts_data(),
+            FILENAME_FOLDERS => folders::update_folders_data(),
+            _ => Ok(()),
+        };
+
+        if let Err(e) = result {
+            eprintln!("Couldn't reload {}: {}", config, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::FileTypes;
+    use crate::filesys::FOLDER_CLIPBOARD;
+    use crate::folders::{get_folders_instance, FolderSettings, SortOrder};
+    use crate::helpers::{test_data_dir, test_items};
+    use crate::history::MimeTypes;
+
+    /// Stores an item with its files the way capture does
+    fn store(folder: &str, name: &str, files: &[&[u8]], formats: &[&str]) -> HistoryItem {
+        let item = HistoryItem::new(
+            name.to_string(),
+            folder.to_string(),
+            &files.concat(),
+            formats.iter().map(|f| f.to_string()).collect(),
+        );
+
+        test_items::store(item, files)
+    }
+
+    fn remove_all_items() {
+        let history = get_history_instance();
+        let mut history = history.lock();
+
+        for file in history.items().iter().flat_map(|item| item.files()) {
+            let _ = fs::remove_file(&file);
+        }
+        history.replace_items(vec![]);
+    }
+
+    fn find(folder: &str, name: &str) -> Option<HistoryItem> {
+        get_history_instance().lock().find(folder, name).cloned()
+    }
+
+    #[test]
+    fn round_trips_items_and_config() {
+        let dir = test_data_dir::new("archive");
+        let archive = dir.path.join("export.zip");
+
+        let rich = store(
+            FOLDER_CLIPBOARD,
+            "100.txt",
+            &[b"bold", b"<b>bold</b>"],
+            &[MimeTypes::TEXT_PLAIN, MimeTypes::TEXT_HTML],
+        );
+        get_history_instance()
+            .lock()
+            .find_mut(FOLDER_CLIPBOARD, "100.txt")
+            .unwrap()
+            .pinned = true;
+
+        store(
+            "work",
+            "200.png",
+            &[b"not really a png"],
+            &[MimeTypes::IMAGE_PNG],
+        );
+
+        let folders = vec![FolderSettings {
+            name: "work".to_string(),
+            created_at: 1,
+            max_count: 5,
+            sort: SortOrder::PasteCount,
+        }];
+        crate::filesys::write_json_data(FILENAME_FOLDERS, &folders);
+
+        let exported = export_archive(&archive).unwrap();
+        assert_eq!(exported.items, 2);
+        assert!(exported.skipped.is_empty());
+        assert_eq!(exported.configs, [FILENAME_FOLDERS]);
+
+        // another machine: nothing stored yet
+        remove_all_items();
+        fs::remove_file(data_dir().join(FILENAME_FOLDERS)).unwrap();
+
+        let imported = import_archive(&archive, ImportMode::Replace).unwrap();
+        assert_eq!(imported.imported, 2);
+        assert_eq!(imported.configs, [FILENAME_FOLDERS]);
+
+        let restored = find(FOLDER_CLIPBOARD, "100.txt").unwrap();
+        assert!(restored.pinned);
+        assert_eq!(restored.hash, rich.hash);
+        assert_eq!(fs::read(restored.path()).unwrap(), b"bold");
+        assert_eq!(
+            fs::read(restored.sidecar_path(FileTypes::HTML)).unwrap(),
+            b"<b>bold</b>"
+        );
+
+        let image = find("work", "200.png").unwrap();
+        assert_eq!(fs::read(image.path()).unwrap(), b"not really a png");
+        assert_eq!(get_folders_instance().lock()[0].max_count, 5);
+    }
+
+    #[test]
+    fn merges_duplicates_and_renames_conflicts() {
+        let dir = test_data_dir::new("archive-merge");
+        let archive = dir.path.join("export.zip");
+
+        store(
+            FOLDER_CLIPBOARD,
+            "100.txt",
+            &[b"same"],
+            &[MimeTypes::TEXT_PLAIN],
+        );
+        store(
+            FOLDER_CLIPBOARD,
+            "200.txt",
+            &[b"exported"],
+            &[MimeTypes::TEXT_PLAIN],
+        );
+        export_archive(&archive).unwrap();
+
+        // 100.txt is moved, 200.txt is replaced by other contents under the same name
+        remove_all_items();
+        store("work", "300.txt", &[b"same"], &[MimeTypes::TEXT_PLAIN]);
+        store(
+            FOLDER_CLIPBOARD,
+            "200.txt",
+            &[b"local"],
+            &[MimeTypes::TEXT_PLAIN],
+        );
+
+        let report = import_archive(&archive, ImportMode::Merge).unwrap();
+        assert_eq!(report.duplicates, ["clipboard/100.txt"]);
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(report.conflicts[0].starts_with("clipboard/200.txt -> clipboard/"));
+
+        assert_eq!(get_history_instance().lock().items().len(), 3);
+        assert_eq!(
+            fs::read(find(FOLDER_CLIPBOARD, "200.txt").unwrap().path()).unwrap(),
+            b"local"
+        );
+
+        // importing the same archive again changes nothing
+        let again = import_archive(&archive, ImportMode::Merge).unwrap();
+        assert_eq!(again.imported, 0);
+        assert_eq!(again.duplicates.len(), 2);
+    }
+
+    #[test]
+    fn replaces_items_only_when_the_archive_is_read() {
+        let dir = test_data_dir::new("archive-replace");
+        let exported = dir.path.join("export.zip");
+        let damaged = dir.path.join("damaged.zip");
+
+        let kept = store(
+            FOLDER_CLIPBOARD,
+            "100.txt",
+            &[b"kept"],
+            &[MimeTypes::TEXT_PLAIN],
+        );
+        export_archive(&exported).unwrap();
+
+        // the manifest lists an item whose file isn't in the archive
+        let missing = HistoryItem::new(
+            "200.txt".to_string(),
+            FOLDER_CLIPBOARD.to_string(),
+            b"lost",
+            vec![MimeTypes::TEXT_PLAIN.to_string()],
+        );
+        let manifest = Manifest {
+            version: ARCHIVE_VERSION,
+            exported_at: 0,
+            items: vec![missing],
+        };
+        let mut zip = ZipWriter::new(File::create(&damaged).unwrap());
+        zip.start_file(FILENAME_MANIFEST, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&serde_json::to_vec(&manifest).unwrap())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let error = import_archive(&damaged, ImportMode::Replace).unwrap_err();
+        assert!(error.contains("nothing was replaced"));
+        assert_eq!(get_history_instance().lock().items().len(), 1);
+        assert_eq!(fs::read(kept.path()).unwrap(), b"kept");
+
+        let added = store(
+            FOLDER_CLIPBOARD,
+            "300.txt",
+            &[b"added"],
+            &[MimeTypes::TEXT_PLAIN],
+        );
+
+        let report = import_archive(&exported, ImportMode::Replace).unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(get_history_instance().lock().items().len(), 1);
+        assert_eq!(
+            fs::read(find(FOLDER_CLIPBOARD, "100.txt").unwrap().path()).unwrap(),
+            b"kept"
+        );
+        assert!(!added.path().exists());
+        assert!(!app_data_dir().join(FOLDER_IMPORT).exists());
+    }
+}
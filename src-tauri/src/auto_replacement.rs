@@ -1,7 +1,7 @@
 use crate::common::KeyValue;
 use crate::filesys::{read_json_data, write_json_data, FILENAME_AUTO_REPLACEMENT};
 use crate::keyboard_layouts::get_current_keyboard_layout_locale;
-use crate::processes::app_active_state;
+use crate::processes::{feature_active, Feature};
 use parking_lot::Mutex;
 use rdev::{listen, Event, EventType, Key as inKey};
 use serde_json::{json, Value};
@@ -141,7 +141,7 @@ pub fn enable_key_listener() {
         .name("auto_replacement:key_listener".to_string())
         .spawn(move || {
             listen(move |event| {
-                if app_active_state() {
+                if feature_active(Feature::AutoReplacement) {
                     handle_event(event);
                 }
             })
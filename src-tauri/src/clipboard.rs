@@ -1,18 +1,37 @@
+use crate::clipboard_monitor;
+use crate::crypto;
 use crate::filesys;
-use crate::keys::send_paste_hotkeys;
-use crate::processes::app_active_state;
+use crate::folders;
+use crate::history;
+use crate::history::MimeTypes;
+use crate::keys::{send_key_times, send_paste_hotkeys};
+use crate::phash::Fingerprint;
+use crate::retention;
+use crate::sensitive;
+use crate::settings::get_settings_instance;
+use crate::templates::{Context, Expanded, Template};
+use crate::transformations::{self, Transformation};
 use arboard::{Clipboard, Error, ImageData};
 use parking_lot::Mutex;
+use rdev::Key;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock};
-use std::thread::sleep;
 use std::time::Duration;
-use std::{fs, thread};
 use tauri::AppHandle;
 use tokio::task;
+use tokio::time::sleep;
 
 pub enum ClipboardContent<'a> {
     Text(String),
+    /// html fragment (and rtf if the source app offered it) with its plain text alternative
+    RichText {
+        text: String,
+        html: String,
+        rtf: Option<String>,
+    },
     Image(ImageData<'a>),
+    /// paths of files copied in a file manager
+    Files(Vec<String>),
 }
 
 #[allow(dead_code)]
@@ -28,14 +47,32 @@ pub struct FileTypes;
 
 impl FileTypes {
     pub const TXT: &'static str = "txt";
+    pub const HTML: &'static str = "html";
+    pub const RTF: &'static str = "rtf";
     pub const PNG: &'static str = "png";
+    pub const FILES: &'static str = "files";
 }
 
 pub static CLIPBOARD: OnceLock<Arc<Mutex<Clipboard>>> = OnceLock::new();
 
 pub static PREV_TEXT: OnceLock<Arc<Mutex<Option<String>>>> = OnceLock::new();
 
-pub static PREV_IMAGE: OnceLock<Arc<Mutex<Option<ImageData>>>> = OnceLock::new();
+/// Fingerprint of the last captured image, see `phash`
+pub static PREV_IMAGE: OnceLock<Arc<Mutex<Option<Fingerprint>>>> = OnceLock::new();
+
+/// Paths of the last captured file list
+pub static PREV_FILES: OnceLock<Arc<Mutex<Option<Vec<String>>>>> = OnceLock::new();
+
+/// Set while we place our own contents on clipboard (paste, restore), they must not be recorded
+static IS_CAPTURE_SUPPRESSED: AtomicBool = AtomicBool::new(false);
+
+pub fn capture_suppressed() -> bool {
+    IS_CAPTURE_SUPPRESSED.load(Ordering::Relaxed)
+}
+
+pub fn set_capture_suppressed(state: bool) {
+    IS_CAPTURE_SUPPRESSED.store(state, Ordering::Relaxed);
+}
 
 pub mod my_clipboard {
     use std::fs;
@@ -44,10 +81,19 @@ pub mod my_clipboard {
     use arboard::Clipboard;
     use tauri::Manager;
 
-    use crate::clipboard::{ClipboardContent, CLIPBOARD};
+    use crate::clipboard::{capture_suppressed, ClipboardContent, FileTypes, CLIPBOARD};
+    use crate::clipboard_hints::{self, Selection};
+    use crate::content_kind;
+    use crate::crypto;
     use crate::filesys;
     use crate::helpers;
     use crate::helpers::get_tauri_handle;
+    use crate::history;
+    use crate::history::HistoryItem;
+    use crate::paste_queue;
+    use crate::processes;
+    use crate::retention;
+    use crate::thumbnails;
     use crate::settings::{get_settings_instance, DEFAULT_MAX_CLIPBOARD_ITEMS};
 
     pub fn get_instance() -> Arc<parking_lot::Mutex<Clipboard>> {
@@ -78,41 +124,171 @@ pub mod my_clipboard {
         }
     }
 
-    pub fn save_contents(contents: ClipboardContent) {
-        let default_folder = "clipboard".to_string();
-        let app = get_tauri_handle().clone();
+    /// Item stored by a capture, `created` is false when an already stored duplicate was bumped instead
+    pub struct Saved {
+        pub item: HistoryItem,
+        pub created: bool,
+    }
 
-        let app_dir = app
-            .path_resolver()
-            .app_local_data_dir()
-            .expect("Failed to resolve app local dir");
+    /// Stores contents taken from `selection` as a new history item, returns the stored or bumped duplicate item
+    pub fn save_contents(contents: ClipboardContent, selection: Selection) -> Option<Saved> {
+        let default_folder = filesys::FOLDER_CLIPBOARD.to_string();
+        let app = get_tauri_handle().clone();
 
-        let p = app_dir.as_path().join("data").join(&default_folder);
+        let p = history::folder_path(&default_folder);
 
         println!("save_clipboard: {}", p.display());
 
-        fs::create_dir_all(&p).unwrap();
+        if let Err(e) = fs::create_dir_all(&p) {
+            eprintln!("save_clipboard: couldn't create {}: {}", p.display(), e);
+            return None;
+        }
 
-        match contents {
+        if crypto::is_enabled() && !crypto::is_unlocked() {
+            println!("save_clipboard: history is locked, skipping");
+            return None;
+        }
+
+        if capture_suppressed() {
+            println!("save_clipboard: our own contents, skipping");
+            return None;
+        }
+
+        if clipboard_hints::should_skip_capture(selection) {
+            println!("save_clipboard: marked as secret by the source app, skipping");
+            return None;
+        }
+
+        let mut dimensions = None;
+        let mut thumbnail = None;
+        let mut kind = None;
+
+        // main file goes first, other formats are stored next to it with the same name
+        let files: Vec<(&str, Vec<u8>)> = match contents {
             ClipboardContent::Text(data) => {
-                let f = p.join([helpers::get_timestamp(), ".txt".to_string()].concat());
-                text::save(&f, &data);
+                kind = Some(content_kind::classify(&data));
+
+                vec![(FileTypes::TXT, text::encode(&data))]
+            }
+            ClipboardContent::RichText { text, html, rtf } => {
+                kind = Some(content_kind::classify(&text));
+
+                let mut files = vec![
+                    (FileTypes::TXT, text::encode(&text)),
+                    (FileTypes::HTML, text::encode(&html)),
+                ];
+                if let Some(rtf) = rtf {
+                    files.push((FileTypes::RTF, text::encode(&rtf)));
+                }
+
+                files
             }
             ClipboardContent::Image(data) => {
-                let f = p.join([helpers::get_timestamp(), ".png".to_string()].concat());
-                image::save(&f, &data).unwrap();
+                dimensions = Some((data.width as u32, data.height as u32));
+                thumbnail = image::thumbnail(&data);
+
+                match image::encode(&data) {
+                    Ok(png) => vec![(FileTypes::PNG, png)],
+                    Err(e) => {
+                        eprintln!("save_clipboard: couldn't encode image: {}", e);
+                        return None;
+                    }
+                }
             }
-        }
+            ClipboardContent::Files(paths) => vec![(FileTypes::FILES, files::encode(&paths))],
+        };
 
-        let settings = get_settings_instance();
-        let settings = settings.lock();
+        let bytes: Vec<u8> = files.iter().flat_map(|(_, b)| b.iter().copied()).collect();
 
-        if settings.clipboard_max_count > 0 {
-            filesys::remove_extra_files(default_folder, settings.clipboard_max_count, &app);
-        } else {
-            filesys::remove_extra_files(default_folder, DEFAULT_MAX_CLIPBOARD_ITEMS, &app);
+        if !retention::fits_item_cap(bytes.len() as u64) {
+            println!("save_clipboard: {} bytes is above the item size cap, skipping", bytes.len());
+            return None;
         }
 
+        if let Some(item) = history::touch_by_hash(&history::content_hash(&bytes)) {
+            println!("save_clipboard: already in history, bumped");
+            emit_contents_changed(&app);
+            paste_queue::push(&item);
+            return Some(Saved {
+                item,
+                created: false,
+            });
+        }
+
+        let source = processes::source_process();
+
+        let item = {
+            // encryption is switched under this lock, so files, hash and index agree on it
+            let history = history::get_history_instance();
+            let mut history = history.lock();
+
+            let timestamp = helpers::get_timestamp();
+            let name = [timestamp.clone(), ".".to_string(), files[0].0.to_string()].concat();
+
+            let mut formats = Vec::new();
+            let mut written = Vec::new();
+            for (extension, data) in files.iter() {
+                let f = p.join([timestamp.clone(), ".".to_string(), extension.to_string()].concat());
+                if let Err(e) = crypto::write_file(&f, data) {
+                    eprintln!("save_clipboard: couldn't write {}: {}", f.display(), e);
+                    // an item without some of its files isn't stored at all
+                    for file in written.iter().chain([&f]) {
+                        let _ = fs::remove_file(file);
+                    }
+                    return None;
+                }
+                written.push(f);
+
+                if let Some(mime) = history::mime_for_extension(extension) {
+                    formats.push(mime.to_string());
+                }
+            }
+
+            let mut item = HistoryItem::new(name, default_folder.clone(), &bytes, formats);
+            item.encrypted = crypto::is_enabled();
+            item.source = source;
+            item.kind = kind;
+            if let Some((width, height)) = dimensions {
+                item.width = Some(width);
+                item.height = Some(height);
+            }
+
+            if let Some(thumbnail) = thumbnail {
+                if let Err(e) = thumbnails::save(&item.hash, &thumbnail) {
+                    eprintln!("save_clipboard: couldn't save thumbnail: {}", e);
+                }
+            }
+
+            history.add(item.clone());
+            history::save_history(&history);
+
+            item
+        };
+
+        let max_count = {
+            let settings = get_settings_instance();
+            let settings = settings.lock();
+
+            if settings.clipboard_max_count > 0 {
+                settings.clipboard_max_count
+            } else {
+                DEFAULT_MAX_CLIPBOARD_ITEMS
+            }
+        };
+
+        filesys::remove_extra_files(default_folder, max_count);
+        retention::enforce_and_notify();
+
+        emit_contents_changed(&app);
+        paste_queue::push(&item);
+
+        Some(Saved {
+            item,
+            created: true,
+        })
+    }
+
+    fn emit_contents_changed(app: &tauri::AppHandle) {
         app.emit_all(
             "clipboard",
             filesys::Payload {
@@ -123,12 +299,12 @@ pub mod my_clipboard {
     }
 
     pub mod text {
-        use std::fs;
-        use std::path::PathBuf;
         use std::sync::Arc;
 
         use crate::clipboard::my_clipboard::get_instance;
         use crate::clipboard::{my_clipboard, ClipboardContent, PREV_TEXT};
+        use crate::clipboard_hints::Selection;
+        use crate::sensitive::{self, SensitivePolicy};
 
         pub fn get_previous() -> Arc<parking_lot::Mutex<Option<String>>> {
             PREV_TEXT
@@ -161,35 +337,79 @@ pub mod my_clipboard {
             clipboard.set_text(text).map_err(|e| e.to_string())
         }
 
-        pub fn save(path: &PathBuf, contents: &String) {
-            fs::write(path, contents).expect("Unable to write file");
+        pub fn encode(contents: &str) -> Vec<u8> {
+            contents.as_bytes().to_vec()
         }
 
         pub fn on_copy() {
+            // file managers offer the paths as text too, what was copied is the files
+            if let Some(paths) = my_clipboard::files::get() {
+                let previous_files = my_clipboard::files::get_previous_files().unwrap();
+                if previous_files.as_ref() != Some(&paths) {
+                    my_clipboard::files::set_previous_files(paths.clone()).unwrap();
+                    my_clipboard::save_contents(ClipboardContent::Files(paths), Selection::Clipboard);
+                }
+                return;
+            }
+
             let clipboard = my_clipboard::get_instance();
             let mut clipboard_lock = clipboard.lock();
 
             match clipboard_lock.get_text() {
                 Ok(text) => {
+                    // the same Ctrl+C may be caught several times, older duplicates are handled by save_contents
                     let previous_text = get_previous_text().unwrap();
-                    match previous_text {
-                        None => {
-                            set_previous_text(text.clone()).unwrap();
-                            my_clipboard::save_contents(ClipboardContent::Text(text));
-                        }
-                        Some(prev_text) => {
-                            if text != prev_text {
-                                my_clipboard::save_contents(ClipboardContent::Text(text));
-                            }
-                        }
+                    if previous_text.as_ref() != Some(&text) {
+                        set_previous_text(text.clone()).unwrap();
+                        drop(clipboard_lock);
+
+                        save_text(text, Selection::Clipboard);
                     }
                 }
                 Err(_) => {
+                    drop(clipboard_lock);
+
                     my_clipboard::image::on_copy();
                 }
             }
         }
 
+        /// Stores copied text applying the policy of sensitive contents (passwords, keys etc.),
+        /// html and rtf are taken too when the text comes from clipboard
+        pub fn save_text(text: String, selection: Selection) {
+            let kind = sensitive::detect(&text);
+
+            match kind.map(sensitive::policy_for) {
+                Some(SensitivePolicy::Skip) => {
+                    println!("on_copy: sensitive contents ({:?}), skipping", kind);
+                }
+                Some(SensitivePolicy::Mask) => {
+                    // formatted alternatives would reveal the contents, so only masked text is stored
+                    let masked = ClipboardContent::Text(sensitive::mask(&text));
+                    my_clipboard::save_contents(masked, selection);
+                }
+                Some(SensitivePolicy::Expire { seconds }) => {
+                    // a stored duplicate may be pinned or kept in a folder on purpose
+                    if let Some(saved) = my_clipboard::save_contents(ClipboardContent::Text(text), selection) {
+                        if saved.created {
+                            sensitive::expire_after(&saved.item, seconds);
+                        }
+                    }
+                }
+                Some(SensitivePolicy::Store) | None => match (selection == Selection::Clipboard)
+                    .then(my_clipboard::html::get)
+                    .flatten()
+                {
+                    Some((html, rtf)) => {
+                        my_clipboard::save_contents(ClipboardContent::RichText { text, html, rtf }, selection);
+                    }
+                    None => {
+                        my_clipboard::save_contents(ClipboardContent::Text(text), selection);
+                    }
+                },
+            }
+        }
+
         //EXAMPLE
         // pub fn spawn_thread_and_write(text: String) -> Result<(), String> {
         //     let clipboard = get_clipboard();
@@ -201,25 +421,543 @@ pub mod my_clipboard {
         // }
     }
 
+    /// X11 PRIMARY selection: text highlighted with mouse and pasted with middle click
+    #[cfg(target_os = "linux")]
+    pub mod primary {
+        use std::sync::{Arc, OnceLock};
+
+        use arboard::{GetExtLinux, LinuxClipboardKind, SetExtLinux};
+        use parking_lot::Mutex;
+
+        use crate::clipboard::capture_suppressed;
+        use crate::clipboard::my_clipboard::{self, get_instance};
+        use crate::clipboard_hints::Selection;
+        use crate::settings::get_settings_instance;
+
+        static PREV_PRIMARY: OnceLock<Arc<Mutex<Option<String>>>> = OnceLock::new();
+
+        fn get_previous() -> Arc<Mutex<Option<String>>> {
+            PREV_PRIMARY
+                .get_or_init(|| Arc::new(Mutex::new(None)))
+                .clone()
+        }
+
+        /// Remembers the selected text, false if it's the same as before
+        fn replace_previous(text: &str) -> bool {
+            let prev = get_previous();
+            let mut prev = prev.lock();
+
+            if prev.as_deref() == Some(text) {
+                return false;
+            }
+
+            *prev = Some(text.to_string());
+            true
+        }
+
+        pub fn get() -> Result<String, String> {
+            let clipboard = get_instance();
+            let mut clipboard = clipboard.lock();
+            clipboard
+                .get()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text()
+                .map_err(|e| e.to_string())
+        }
+
+        pub fn set(text: String) -> Result<(), String> {
+            let clipboard = get_instance();
+            let mut clipboard = clipboard.lock();
+            clipboard
+                .set()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text(text)
+                .map_err(|e| e.to_string())
+        }
+
+        /// Called once the selection stopped changing, stores it and copies it to clipboard if enabled
+        pub fn on_select() {
+            let (capture, sync) = {
+                let settings = get_settings_instance();
+                let settings = settings.lock();
+                (
+                    settings.capture_primary_selection,
+                    settings.sync_primary_selection,
+                )
+            };
+
+            if !(capture || sync) || capture_suppressed() {
+                return;
+            }
+
+            let Ok(text) = get() else {
+                return;
+            };
+            if text.trim().is_empty() || !replace_previous(&text) {
+                return;
+            }
+
+            if sync && my_clipboard::text::get().ok().as_ref() != Some(&text) {
+                // synced text isn't a copy, the clipboard monitor skips it as already seen
+                my_clipboard::text::set_previous_text(text.clone()).unwrap();
+                if let Err(e) = my_clipboard::text::set(text.clone()) {
+                    eprintln!("primary: couldn't sync to clipboard: {}", e);
+                }
+            }
+
+            if capture {
+                my_clipboard::text::save_text(text, Selection::Primary);
+            }
+        }
+
+        /// Copies clipboard text to PRIMARY if sync is enabled, called on clipboard changes
+        pub fn sync_from_clipboard() {
+            if !get_settings_instance().lock().sync_primary_selection || capture_suppressed() {
+                return;
+            }
+
+            let Ok(text) = my_clipboard::text::get() else {
+                return;
+            };
+            if get().ok().as_ref() == Some(&text) {
+                return;
+            }
+
+            // not a new selection, so it isn't captured
+            replace_previous(&text);
+            if let Err(e) = set(text) {
+                eprintln!("primary: couldn't sync from clipboard: {}", e);
+            }
+        }
+    }
+
+    pub mod html {
+        #[cfg(not(target_os = "windows"))]
+        use crate::history::MimeTypes;
+
+        /// Registered names of the windows clipboard formats
+        pub const CF_HTML: &str = "HTML Format";
+        pub const CF_RTF: &str = "Rich Text Format";
+
+        /// Some apps offer rtf under this name instead of `text/rtf`
+        #[cfg(not(target_os = "windows"))]
+        const MIME_APPLICATION_RTF: &str = "application/rtf";
+        #[cfg(not(target_os = "windows"))]
+        const MIME_TEXT_UTF8: &str = "text/plain;charset=utf-8";
+
+        /// Returns html fragment and rtf of current clipboard contents if the source app offered them
+        #[cfg(target_os = "windows")]
+        pub fn get() -> Option<(String, Option<String>)> {
+            let _clip = clipboard_win::Clipboard::new_attempts(10).ok()?;
+
+            let html = read_format(CF_HTML).and_then(|cf_html| fragment(&cf_html))?;
+            let rtf = read_format(CF_RTF);
+
+            Some((html, rtf))
+        }
+
+        /// Returns html and rtf targets of current clipboard contents if the source app offered them
+        #[cfg(not(target_os = "windows"))]
+        pub fn get() -> Option<(String, Option<String>)> {
+            let selection = crate::x11::X11Selection::new("CLIPBOARD").ok()?;
+            let targets = selection.targets().ok()?;
+
+            let read = |target: &str| {
+                targets
+                    .iter()
+                    .any(|t| t == target)
+                    .then(|| selection.convert(target).ok())
+                    .flatten()
+                    .map(|data| decode(&data))
+            };
+
+            let html = read(MimeTypes::TEXT_HTML)?;
+            let rtf = read(MimeTypes::TEXT_RTF).or_else(|| read(MIME_APPLICATION_RTF));
+
+            Some((html, rtf))
+        }
+
+        /// Places html with its plain text alternative, and rtf if there is one
+        #[cfg(target_os = "windows")]
+        pub fn set(html: &str, text: &str, rtf: Option<&str>) -> Result<(), String> {
+            let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+            clipboard.set_html(html, Some(text)).map_err(|e| e.to_string())?;
+
+            match rtf {
+                Some(rtf) => add_rtf(rtf),
+                None => Ok(()),
+            }
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        pub fn set(html: &str, text: &str, rtf: Option<&str>) -> Result<(), String> {
+            let mut targets = vec![
+                (MimeTypes::TEXT_HTML, html.as_bytes()),
+                ("UTF8_STRING", text.as_bytes()),
+                (MIME_TEXT_UTF8, text.as_bytes()),
+            ];
+            if let Some(rtf) = rtf {
+                targets.push((MimeTypes::TEXT_RTF, rtf.as_bytes()));
+            }
+
+            crate::clipboard_persistence::offer(&targets)
+        }
+
+        /// Adds rtf to the formats already placed on clipboard, must be called right after `set_html`
+        #[cfg(target_os = "windows")]
+        fn add_rtf(rtf: &str) -> Result<(), String> {
+            let _clip = clipboard_win::Clipboard::new_attempts(10).map_err(|e| e.to_string())?;
+            let format = clipboard_win::register_format(CF_RTF).ok_or("Couldn't register rtf format")?;
+
+            clipboard_win::raw::set_without_clear(format.get(), rtf.as_bytes()).map_err(|e| e.to_string())
+        }
+
+        /// X11 targets are 8-bit text, but browsers used to offer `text/html` as UTF-16 with a BOM
+        pub fn decode(data: &[u8]) -> String {
+            let text = match data {
+                [0xFF, 0xFE, rest @ ..] => {
+                    let units: Vec<u16> = rest
+                        .chunks_exact(2)
+                        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                        .collect();
+                    String::from_utf16_lossy(&units)
+                }
+                _ => String::from_utf8_lossy(data).to_string(),
+            };
+
+            text.trim_end_matches('\0').to_string()
+        }
+
+        #[cfg(target_os = "windows")]
+        fn read_format(name: &str) -> Option<String> {
+            let format = clipboard_win::register_format(name)?;
+            if !clipboard_win::is_format_avail(format.get()) {
+                return None;
+            }
+
+            let mut data = Vec::new();
+            clipboard_win::raw::get_vec(format.get(), &mut data).ok()?;
+
+            Some(String::from_utf8_lossy(&data).trim_end_matches('\0').to_string())
+        }
+
+        /// Extracts the copied fragment from CF_HTML, which wraps it with a header and a full document:
+        /// `Version:0.9\r\nStartHTML:..\r\nStartFragment:00000123\r\nEndFragment:00000456\r\n<html>..`
+        pub fn fragment(cf_html: &str) -> Option<String> {
+            let offset = |key: &str| -> Option<usize> {
+                let start = cf_html.find(key)? + key.len();
+                let value: String = cf_html[start..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                value.parse().ok()
+            };
+
+            let start = offset("StartFragment:")?;
+            let end = offset("EndFragment:")?;
+
+            cf_html
+                .as_bytes()
+                .get(start..end)
+                .map(|f| String::from_utf8_lossy(f).to_string())
+        }
+    }
+
+    pub mod files {
+        use crate::clipboard::PREV_FILES;
+        #[cfg(not(target_os = "windows"))]
+        use crate::history::MimeTypes;
+        use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+        use serde::{Deserialize, Serialize};
+        use std::fs;
+        use std::path::Path;
+        use std::sync::Arc;
+
+        /// Unreserved chars and `/` stay as they are in a `file://` uri
+        const URI_PATH: &AsciiSet = &NON_ALPHANUMERIC
+            .remove(b'-')
+            .remove(b'.')
+            .remove(b'_')
+            .remove(b'~')
+            .remove(b'/');
+
+        /// Nautilus, Nemo and Caja paste files from it, `copy` or `cut` followed by uris
+        #[cfg(not(target_os = "windows"))]
+        const MIME_GNOME_FILES: &str = "x-special/gnome-copied-files";
+
+        #[derive(Debug, Serialize, Deserialize)]
+        pub struct FileEntry {
+            pub name: String,
+            pub path: String,
+            pub size: Option<u64>,
+            pub exists: bool,
+        }
+
+        impl FileEntry {
+            pub fn from_path(path: &str) -> Self {
+                let metadata = fs::metadata(path).ok();
+
+                FileEntry {
+                    name: Path::new(path)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or(path.to_string()),
+                    path: path.to_string(),
+                    size: metadata.as_ref().filter(|m| m.is_file()).map(|m| m.len()),
+                    exists: metadata.is_some(),
+                }
+            }
+        }
+
+        pub fn get_previous() -> Arc<parking_lot::Mutex<Option<Vec<String>>>> {
+            PREV_FILES
+                .get_or_init(|| Arc::new(parking_lot::Mutex::new(None)))
+                .clone()
+        }
+
+        pub fn get_previous_files() -> Result<Option<Vec<String>>, String> {
+            let prev_files = get_previous();
+            let prev_files = prev_files.lock();
+            Ok(prev_files.clone())
+        }
+
+        pub fn set_previous_files(paths: Vec<String>) -> Result<(), String> {
+            let prev_files = get_previous();
+            let mut prev_files = prev_files.lock();
+            *prev_files = Some(paths);
+            Ok(())
+        }
+
+        pub fn encode(paths: &[String]) -> Vec<u8> {
+            serde_json::to_vec(paths).unwrap_or_default()
+        }
+
+        pub fn decode(bytes: &[u8]) -> Vec<String> {
+            serde_json::from_slice(bytes).unwrap_or_default()
+        }
+
+        /// Paths from CF_HDROP, None if clipboard doesn't hold a file list
+        #[cfg(target_os = "windows")]
+        pub fn get() -> Option<Vec<String>> {
+            clipboard_win::get_clipboard::<Vec<String>, _>(clipboard_win::formats::FileList)
+                .ok()
+                .filter(|paths| !paths.is_empty())
+        }
+
+        /// Paths from `text/uri-list`, None if clipboard doesn't hold a file list
+        #[cfg(not(target_os = "windows"))]
+        pub fn get() -> Option<Vec<String>> {
+            let selection = crate::x11::X11Selection::new("CLIPBOARD").ok()?;
+            if !selection.targets().ok()?.iter().any(|t| t == MimeTypes::URI_LIST) {
+                return None;
+            }
+
+            let uri_list = selection.convert(MimeTypes::URI_LIST).ok()?;
+            Some(parse_uri_list(&String::from_utf8_lossy(&uri_list))).filter(|paths| !paths.is_empty())
+        }
+
+        /// Places CF_HDROP on clipboard so files can be pasted into explorer
+        #[cfg(target_os = "windows")]
+        pub fn set(paths: &[String]) -> Result<(), String> {
+            use crate::helpers::to_wide_string;
+
+            // DROPFILES header: offset of the file list, drop point (x, y), fNC, fWide
+            let mut data: Vec<u8> = Vec::new();
+            for value in [20u32, 0, 0, 0, 1] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+
+            // each path is null-terminated, the list ends with an extra null
+            for path in paths {
+                for c in to_wide_string(path) {
+                    data.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            data.extend_from_slice(&0u16.to_le_bytes());
+
+            let _clip = clipboard_win::Clipboard::new_attempts(10).map_err(|e| e.to_string())?;
+            clipboard_win::raw::set(clipboard_win::formats::CF_HDROP, &data).map_err(|e| e.to_string())?;
+
+            // tells explorer to copy files rather than move them
+            if let Some(format) = clipboard_win::register_format("Preferred DropEffect") {
+                let drop_effect_copy: u32 = 1;
+                clipboard_win::raw::set_without_clear(format.get(), &drop_effect_copy.to_le_bytes())
+                    .map_err(|e| e.to_string())?;
+            }
+
+            Ok(())
+        }
+
+        /// Places `text/uri-list` on clipboard, with the gnome variant file managers paste from
+        #[cfg(not(target_os = "windows"))]
+        pub fn set(paths: &[String]) -> Result<(), String> {
+            let uri_list = uri_list(paths);
+            let gnome_files = format!("copy\n{}", uri_list.trim_end().replace("\r\n", "\n"));
+            let text = paths.join("\n");
+
+            crate::clipboard_persistence::offer(&[
+                (MimeTypes::URI_LIST, uri_list.as_bytes()),
+                (MIME_GNOME_FILES, gnome_files.as_bytes()),
+                ("UTF8_STRING", text.as_bytes()),
+            ])
+        }
+
+        /// Local paths of `file://` uris, one per line, `#` starts a comment (RFC 2483)
+        pub fn parse_uri_list(uri_list: &str) -> Vec<String> {
+            uri_list
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|uri| uri.strip_prefix("file://"))
+                // `file:///path` has an empty host, `file://localhost/path` is the same file
+                .filter_map(|rest| rest.strip_prefix("localhost").unwrap_or(rest).strip_prefix('/'))
+                .map(|path| format!("/{}", percent_decode_str(path).decode_utf8_lossy()))
+                .collect()
+        }
+
+        /// `text/uri-list` of `paths`, lines end with CRLF as the format requires
+        pub fn uri_list(paths: &[String]) -> String {
+            paths
+                .iter()
+                .map(|path| format!("file://{}\r\n", utf8_percent_encode(path, URI_PATH)))
+                .collect()
+        }
+    }
+
+    /// Copy of everything on clipboard, used to give the user's clipboard back after a paste from history
+    pub mod snapshot {
+        #[cfg(target_os = "windows")]
+        pub struct Snapshot {
+            formats: Vec<(u32, Vec<u8>)>,
+        }
+
+        /// Formats holding GDI handles or private data instead of global memory can't be copied as bytes
+        #[cfg(target_os = "windows")]
+        fn is_copyable(format: u32) -> bool {
+            use clipboard_win::formats::*;
+
+            !matches!(
+                format,
+                CF_BITMAP
+                    | CF_ENHMETAFILE
+                    | CF_METAFILEPICT
+                    | CF_PALETTE
+                    | CF_OWNERDISPLAY
+                    | CF_DSPBITMAP
+                    | CF_DSPENHMETAFILE
+                    | CF_DSPMETAFILEPICT
+            ) && !(CF_PRIVATEFIRST..=CF_PRIVATELAST).contains(&format)
+                && !(CF_GDIOBJFIRST..=CF_GDIOBJLAST).contains(&format)
+        }
+
+        #[cfg(target_os = "windows")]
+        pub fn take() -> Option<Snapshot> {
+            let _clip = clipboard_win::Clipboard::new_attempts(10).ok()?;
+
+            let formats = clipboard_win::EnumFormats::new()
+                .filter(|format| is_copyable(*format))
+                .filter_map(|format| {
+                    let mut data = Vec::new();
+                    clipboard_win::raw::get_vec(format, &mut data).ok()?;
+                    Some((format, data))
+                })
+                .collect();
+
+            Some(Snapshot { formats })
+        }
+
+        #[cfg(target_os = "windows")]
+        impl Snapshot {
+            pub fn restore(&self) -> Result<(), String> {
+                let _clip = clipboard_win::Clipboard::new_attempts(10).map_err(|e| e.to_string())?;
+                clipboard_win::raw::empty().map_err(|e| e.to_string())?;
+
+                for (format, data) in self.formats.iter() {
+                    clipboard_win::raw::set_without_clear(*format, data).map_err(|e| e.to_string())?;
+                }
+
+                Ok(())
+            }
+        }
+
+        /// Every X11 target of the clipboard, targets too big for a single request are left out
+        #[cfg(not(target_os = "windows"))]
+        pub struct Snapshot {
+            targets: Vec<crate::clipboard_persistence::Target>,
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        pub fn take() -> Option<Snapshot> {
+            let clipboard = super::get_instance();
+            let _clipboard = clipboard.lock();
+
+            match crate::clipboard_persistence::snapshot() {
+                Ok(targets) => Some(Snapshot { targets }),
+                Err(e) => {
+                    eprintln!("snapshot: {}", e);
+                    None
+                }
+            }
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        impl Snapshot {
+            pub fn restore(&self) -> Result<(), String> {
+                let clipboard = super::get_instance();
+                let mut clipboard = clipboard.lock();
+
+                if self.targets.is_empty() {
+                    return clipboard.clear().map_err(|e| e.to_string());
+                }
+
+                crate::clipboard_persistence::restore(self.targets.clone())
+            }
+        }
+
+        /// Marks current clipboard contents as already seen, so restored contents aren't captured again
+        pub fn remember_current() {
+            if let Some(paths) = super::files::get() {
+                let _ = super::files::set_previous_files(paths);
+            }
+
+            if let Ok(text) = super::text::get() {
+                let _ = super::text::set_previous_text(text);
+            }
+
+            let clipboard = super::get_instance();
+            let image = clipboard.lock().get_image();
+            if let Some(fingerprint) = image.ok().and_then(|i| super::image::fingerprint(&i)) {
+                let _ = super::image::set_prev_image(fingerprint);
+            }
+        }
+    }
+
     pub mod image {
-        use std::path::PathBuf;
+        use std::io::Cursor;
         use std::sync::Arc;
 
-        use crate::clipboard::{my_clipboard, ClipboardContent, PREV_IMAGE};
+        use crate::clipboard::{capture_suppressed, my_clipboard, ClipboardContent, PREV_IMAGE};
+        use crate::clipboard_hints::Selection;
+        use crate::helpers::get_tauri_handle;
+        use crate::history;
+        use crate::paste_queue;
+        use crate::phash::Fingerprint;
+        use crate::settings::get_settings_instance;
+        use crate::thumbnails;
         use arboard::ImageData;
-        use image::{ImageBuffer, Rgba};
+        use image::{DynamicImage, ImageBuffer, Rgba};
         // use crate::{ClipboardContent, my_clipboard, PREV_IMAGE};
 
-        pub fn get_previous() -> Arc<parking_lot::Mutex<Option<ImageData<'static>>>> {
+        pub fn get_previous() -> Arc<parking_lot::Mutex<Option<Fingerprint>>> {
             PREV_IMAGE
                 .get_or_init(|| Arc::new(parking_lot::Mutex::new(None)))
                 .clone()
         }
 
-        pub fn get_prev_image_data() -> Result<Option<ImageData<'static>>, String> {
+        pub fn get_prev_image() -> Result<Option<Fingerprint>, String> {
             let prev_image = get_previous();
             let prev_image = prev_image.lock();
-            Ok(prev_image.clone())
+            Ok(*prev_image)
         }
 
         pub fn init_prev_image() -> Result<(), String> {
@@ -229,23 +967,22 @@ pub mod my_clipboard {
             Ok(())
         }
 
-        pub fn set_prev_image(image_data: ImageData<'static>) -> Result<(), String> {
+        pub fn set_prev_image(fingerprint: Fingerprint) -> Result<(), String> {
             let prev_image = get_previous();
             let mut prev_image = prev_image.lock();
-            *prev_image = Some(image_data);
+            *prev_image = Some(fingerprint);
             Ok(())
         }
 
-        pub fn eq(image_data1: &ImageData, image_data2: &ImageData) -> bool {
-            image_data1.width == image_data2.width
-                && image_data1.height == image_data2.height
-                && image_data1.bytes[..] == image_data2.bytes[..]
+        pub fn fingerprint(image_data: &ImageData) -> Option<Fingerprint> {
+            Fingerprint::of_rgba(
+                image_data.width as u32,
+                image_data.height as u32,
+                &image_data.bytes[..],
+            )
         }
 
-        pub fn save(
-            path: &PathBuf,
-            image_data: &ImageData,
-        ) -> Result<(), Box<dyn std::error::Error>> {
+        pub fn encode(image_data: &ImageData) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
             let buffer: ImageBuffer<Rgba<u8>, _> = ImageBuffer::from_raw(
                 image_data.width as u32,
                 image_data.height as u32,
@@ -253,11 +990,21 @@ pub mod my_clipboard {
             )
             .ok_or("Failed to create image buffer")?;
 
-            buffer
-                .save_with_format(path.as_path(), image::ImageFormat::Png)
-                .unwrap();
+            let mut bytes: Vec<u8> = Vec::new();
+            buffer.write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)?;
 
-            Ok(())
+            Ok(bytes)
+        }
+
+        /// Thumbnail made from raw pixels, so the png doesn't have to be decoded again
+        pub fn thumbnail(image_data: &ImageData) -> Option<Vec<u8>> {
+            let buffer: ImageBuffer<Rgba<u8>, _> = ImageBuffer::from_raw(
+                image_data.width as u32,
+                image_data.height as u32,
+                image_data.bytes.to_vec(),
+            )?;
+
+            thumbnails::make(&DynamicImage::ImageRgba8(buffer)).ok()
         }
 
         // EXAMPLE
@@ -271,61 +1018,102 @@ pub mod my_clipboard {
         // }
 
         pub fn on_copy() {
+            if capture_suppressed() {
+                return;
+            }
+
             let clipboard = my_clipboard::get_instance();
             let mut clipboard_lock = clipboard.lock();
-            let image_data = clipboard_lock.get_image().unwrap();
+            let Ok(image_data) = clipboard_lock.get_image() else {
+                return;
+            };
+            drop(clipboard_lock);
 
-            let previous_image = get_prev_image_data().unwrap();
+            let Some(fingerprint) = fingerprint(&image_data) else {
+                return;
+            };
 
-            match previous_image {
-                None => {
-                    set_prev_image(image_data.clone()).expect("set_prev_image error");
-                    my_clipboard::save_contents(ClipboardContent::Image(image_data));
-                }
-                Some(ref i) => {
-                    if !eq(&i, &image_data) {
-                        set_prev_image(image_data.clone()).expect("set_prev_image error");
-                        my_clipboard::save_contents(ClipboardContent::Image(image_data));
-                    }
+            let threshold = get_settings_instance().lock().image_similarity_threshold;
+
+            let previous = get_prev_image().unwrap();
+            if previous.is_some_and(|p| p.matches(&fingerprint, threshold)) {
+                return;
+            }
+            set_prev_image(fingerprint).expect("set_prev_image error");
+
+            // re-encoded or slightly changed copy of a stored image, identical ones are found by content hash
+            if threshold > 0 {
+                if let Some(item) = history::touch_similar_image(&fingerprint, threshold) {
+                    println!("save_clipboard: similar image already in history, bumped");
+                    my_clipboard::emit_contents_changed(get_tauri_handle());
+                    paste_queue::push(&item);
+                    return;
                 }
             }
+
+            if let Some(my_clipboard::Saved {
+                item,
+                created: true,
+            }) = my_clipboard::save_contents(ClipboardContent::Image(image_data), Selection::Clipboard)
+            {
+                history::update_item(&item.folder, &item.name, |stored| stored.phash = Some(fingerprint.dhash));
+            }
         }
     }
 }
 
-pub static IMG_THREAD_INTERVAL: u64 = 1000;
-
 pub fn enable_clipboard() -> Result<(), String> {
     filesys::create_folders(&[filesys::FOLDER_CLIPBOARD, filesys::FOLDER_FAVOURITES])
         .expect("Couldn't create required directories");
 
-    // image can get to clipboard in many ways, so we use interval-based checker
-    let _ = thread::Builder::new()
-        .name("clipboard:image_checker".to_string())
-        .spawn(move || {
-            my_clipboard::image::init_prev_image().unwrap();
+    crypto::load_encryption();
+    history::load_history();
+    let _ = folders::update_folders_data();
+    sensitive::remove_expired_on_start();
+    retention::run_janitor();
+    clipboard_monitor::run();
+    #[cfg(target_os = "linux")]
+    crate::clipboard_persistence::run();
 
-            loop {
-                if !app_active_state() {
-                    continue;
-                }
+    Ok(())
+}
 
-                sleep(Duration::from_millis(IMG_THREAD_INTERVAL));
+#[tauri::command]
+pub async fn paste(item: ClipboardItem, app: AppHandle) {
+    if let Err(e) = paste_item(&item.folder, &item.name, &[], app).await {
+        eprintln!("paste: {}", e);
+    }
+}
 
-                if !my_clipboard::has_image() {
-                    continue;
-                }
+/// Pastes text of an item changed by transformations applied in the given order, formatting is dropped
+#[tauri::command]
+pub async fn paste_transformed(
+    item: ClipboardItem,
+    transformations: Vec<Transformation>,
+    app: AppHandle,
+) -> Result<(), String> {
+    paste_item(&item.folder, &item.name, &transformations, app).await
+}
 
-                // TODO: will get clipboard and prev_image instances every cycle - mb profile it?
-                my_clipboard::image::on_copy();
-            }
-        });
+/// Expanded text of a template, None if the text has no placeholders
+fn expand_template(path: &std::path::Path) -> Result<Option<Expanded>, String> {
+    let template = Template::parse(&crypto::read_to_string(path)?)?;
+    if !template.has_placeholders() {
+        return Ok(None);
+    }
 
-    Ok(())
+    let clipboard = my_clipboard::text::get().unwrap_or_default();
+
+    template.expand(&Context::new(clipboard)).map(Some)
 }
 
-#[tauri::command]
-pub async fn paste(item: ClipboardItem, app: AppHandle) {
+/// Places a stored item on clipboard and sends paste hotkeys
+pub async fn paste_item(
+    folder: &str,
+    name: &str,
+    transformations: &[Transformation],
+    app: AppHandle,
+) -> Result<(), String> {
     // window::hide_window(app);
     // sleep(Duration::from_millis(50));
     let from = app
@@ -334,18 +1122,82 @@ pub async fn paste(item: ClipboardItem, app: AppHandle) {
         .expect("Failed to resolve app local dir")
         .as_path()
         .join("data")
-        .join(&item.folder)
-        .join(&item.name);
+        .join(folder)
+        .join(name);
+
+    let stored = history::get_history_instance()
+        .lock()
+        .find(folder, name)
+        .cloned();
 
-    task::spawn(async move {
+    let is_text = from.extension().is_some_and(|e| e == FileTypes::TXT);
+
+    // only favorites marked as templates are expanded, {clipboard} is read before clipboard is replaced
+    let is_template = stored.as_ref().is_some_and(|item| item.template);
+    let expanded = if folder == filesys::FOLDER_FAVOURITES && is_text && is_template {
+        // a broken template is still pasted, just as it is written
+        expand_template(&from).unwrap_or_else(|e| {
+            eprintln!("Pasting template {} as plain text: {}", name, e);
+            None
+        })
+    } else {
+        None
+    };
+    let cursor_moves = expanded.as_ref().and_then(|e| e.cursor_moves);
+    let expanded = expanded.map(|e| e.text);
+
+    let plain_text = match transformations {
+        [] => expanded,
+        _ if is_text => {
+            let text = match expanded {
+                Some(text) => text,
+                None => crypto::read_to_string(&from)?,
+            };
+            Some(transformations::apply_all(&text, transformations)?)
+        }
+        _ => return Err("Only text can be transformed".to_string()),
+    };
+
+    let (restore, delay) = {
+        let settings = get_settings_instance();
+        let settings = settings.lock();
+
+        (settings.restore_clipboard_after_paste, settings.paste_restore_delay_ms)
+    };
+
+    let snapshot = if restore { my_clipboard::snapshot::take() } else { None };
+    set_capture_suppressed(true);
+
+    let pasted = task::spawn(async move {
         let mut clipboard = Clipboard::new().expect("Couldn't create Clipboard instance");
+
+        if let Some(text) = plain_text {
+            clipboard.set_text(text).unwrap();
+            return Ok(());
+        }
+
         match from.extension().unwrap().to_str().unwrap() {
             FileTypes::TXT => {
-                let content = fs::read_to_string(from).unwrap();
-                clipboard.set_html(&content, Some(&content)).unwrap();
+                let content = crypto::read_to_string(&from).unwrap();
+
+                match stored.filter(|s| s.has_format(MimeTypes::TEXT_HTML)) {
+                    Some(stored) => {
+                        let html = crypto::read_to_string(&stored.sidecar_path(FileTypes::HTML)).unwrap();
+                        let rtf = stored
+                            .has_format(MimeTypes::TEXT_RTF)
+                            .then(|| crypto::read_to_string(&stored.sidecar_path(FileTypes::RTF)).ok())
+                            .flatten();
+
+                        if let Err(e) = my_clipboard::html::set(&html, &content, rtf.as_deref()) {
+                            eprintln!("{}", e);
+                            clipboard.set_text(&content).unwrap();
+                        }
+                    }
+                    None => clipboard.set_text(&content).unwrap(),
+                }
             }
             FileTypes::PNG => {
-                let img = image::io::Reader::open(from).unwrap().decode().unwrap();
+                let img = image::load_from_memory(&crypto::read_file(&from).unwrap()).unwrap();
                 let rgba_image = img.to_rgba8();
                 let (width, height) = rgba_image.dimensions();
                 let bytes = rgba_image.into_raw();
@@ -357,16 +1209,55 @@ pub async fn paste(item: ClipboardItem, app: AppHandle) {
 
                 clipboard.set_image(image_data).unwrap();
             }
+            FileTypes::FILES => {
+                let paths: Vec<String> = my_clipboard::files::decode(&crypto::read_file(&from).unwrap())
+                    .into_iter()
+                    .filter(|p| std::path::Path::new(p).exists())
+                    .collect();
+
+                // nothing is pasted, the target app would get whatever was on clipboard before
+                if paths.is_empty() {
+                    return Err("None of the copied files exist anymore".to_string());
+                }
+
+                my_clipboard::files::set(&paths)?;
+            }
             &_ => {}
         }
+
+        Ok(())
     })
     .await
-    .unwrap();
+    .map_err(|e| e.to_string())
+    .and_then(|pasted| pasted);
+
+    // clipboard is restored and capture resumed whatever fails on the way
+    let sent = match pasted {
+        Ok(()) => {
+            history::mark_pasted(folder, name);
+
+            send_paste_hotkeys();
+
+            match cursor_moves {
+                Some(moves) => send_key_times(Key::LeftArrow, moves as i32),
+                None => Ok(()),
+            }
+        }
+        Err(e) => Err(e),
+    };
+
+    // target app reads clipboard some time after Ctrl+V, restoring too early pastes the old contents
+    sleep(Duration::from_millis(delay)).await;
 
-    send_paste_hotkeys();
+    let restored = match snapshot {
+        Some(snapshot) => snapshot.restore(),
+        None => clipboard_clear().map_err(|e| e.to_string()),
+    };
 
-    sleep(Duration::from_millis(50));
-    clipboard_clear().unwrap();
+    my_clipboard::snapshot::remember_current();
+    set_capture_suppressed(false);
+
+    sent.and(restored)
 }
 
 fn clipboard_clear() -> Result<(), Error> {
@@ -375,3 +1266,78 @@ fn clipboard_clear() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::my_clipboard::{files, html};
+    #[cfg(target_os = "linux")]
+    use super::my_clipboard::{primary, text};
+    #[cfg(target_os = "linux")]
+    use crate::settings::get_settings_instance;
+
+    #[test]
+    fn decodes_html_targets() {
+        assert_eq!(html::decode(b"<b>bold</b>\0"), "<b>bold</b>");
+
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("<i>ü</i>".encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect();
+        assert_eq!(html::decode(&utf16), "<i>ü</i>");
+    }
+
+    #[test]
+    fn extracts_cf_html_fragment() {
+        let cf_html = "Version:0.9\r\nStartFragment:00000071\r\nEndFragment:00000082\r\n<html><body><b>bold</b></body></html>";
+
+        assert_eq!(html::fragment(cf_html).as_deref(), Some("<b>bold</b>"));
+        assert_eq!(html::fragment("<b>no header</b>"), None);
+    }
+
+    #[test]
+    fn parses_uri_lists() {
+        let uri_list = "# copied by a file manager\r\nfile:///home/me/My%20Notes.txt\r\nfile://localhost/tmp/a%2Bb\r\n\r\nhttps://example.com/x\r\nfile:///home/me/%D1%84%D0%B0%D0%B9%D0%BB\r\n";
+
+        assert_eq!(
+            files::parse_uri_list(uri_list),
+            ["/home/me/My Notes.txt", "/tmp/a+b", "/home/me/файл"]
+        );
+        // remote hosts aren't local files, a stray % is kept as is
+        assert_eq!(
+            files::parse_uri_list("file://server/share/x\nfile:///100%.txt"),
+            ["/100%.txt"]
+        );
+    }
+
+    #[test]
+    fn produces_uri_lists() {
+        let paths = ["/home/me/My Notes.txt".to_string(), "/tmp/файл#1".to_string()];
+        let uri_list = files::uri_list(&paths);
+
+        assert_eq!(
+            uri_list,
+            "file:///home/me/My%20Notes.txt\r\nfile:///tmp/%D1%84%D0%B0%D0%B9%D0%BB%231\r\n"
+        );
+        assert_eq!(files::parse_uri_list(&uri_list), paths);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn syncs_primary_selection_and_clipboard() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        get_settings_instance().lock().sync_primary_selection = true;
+
+        text::set("copied".to_string()).unwrap();
+        primary::sync_from_clipboard();
+        assert_eq!(primary::get().unwrap(), "copied");
+
+        primary::set("selected".to_string()).unwrap();
+        primary::on_select();
+        assert_eq!(text::get().unwrap(), "selected");
+
+        get_settings_instance().lock().sync_primary_selection = false;
+    }
+}
@@ -0,0 +1,236 @@
+/// Formats password managers offer next to secret copies, asking clipboard managers not to record them.
+/// Windows: presence of the format is enough
+pub const EXCLUDE_FROM_MONITOR: &str = "ExcludeClipboardContentFromMonitorProcessing";
+/// Windows: presence of the format is enough, older convention
+pub const CLIPBOARD_VIEWER_IGNORE: &str = "Clipboard Viewer Ignore";
+/// Windows: DWORD, 0 means don't record
+pub const CAN_INCLUDE_IN_HISTORY: &str = "CanIncludeInClipboardHistory";
+/// Linux (KDE, KeePassXC): `secret` means don't record
+pub const KDE_PASSWORD_MANAGER_HINT: &str = "x-kde-passwordManagerHint";
+
+/// Formats (windows) or targets (x11) offered by the current clipboard owner
+pub trait OfferedFormats {
+    fn formats(&self) -> Vec<String>;
+    fn get(&self, format: &str) -> Option<Vec<u8>>;
+}
+
+/// True if contents are marked as secret and must not be recorded
+pub fn is_secret(clipboard: &impl OfferedFormats) -> bool {
+    let formats = clipboard.formats();
+    let offers = |name: &str| formats.iter().any(|f| f == name);
+
+    if offers(EXCLUDE_FROM_MONITOR) || offers(CLIPBOARD_VIEWER_IGNORE) {
+        return true;
+    }
+
+    if offers(CAN_INCLUDE_IN_HISTORY)
+        && clipboard
+            .get(CAN_INCLUDE_IN_HISTORY)
+            .and_then(|data| data.get(..4).map(|d| u32::from_le_bytes([d[0], d[1], d[2], d[3]])))
+            == Some(0)
+    {
+        return true;
+    }
+
+    offers(KDE_PASSWORD_MANAGER_HINT)
+        && clipboard
+            .get(KDE_PASSWORD_MANAGER_HINT)
+            .is_some_and(|data| String::from_utf8_lossy(&data).trim_matches(['\0', ' ', '\n']) == "secret")
+}
+
+/// Where captured contents come from, PRIMARY (text selected with mouse) exists on X11 only
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+}
+
+impl Selection {
+    #[cfg(target_os = "linux")]
+    pub fn x11_name(&self) -> &'static str {
+        match self {
+            Selection::Clipboard => "CLIPBOARD",
+            Selection::Primary => "PRIMARY",
+        }
+    }
+}
+
+/// Checks hints of the selection contents are captured from
+pub fn should_skip_capture(selection: Selection) -> bool {
+    match SystemClipboard::open(selection) {
+        Ok(clipboard) => is_secret(&clipboard),
+        Err(e) => {
+            eprintln!("clipboard hints: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub struct SystemClipboard;
+
+#[cfg(target_os = "windows")]
+impl SystemClipboard {
+    pub fn open(_selection: Selection) -> Result<Self, String> {
+        Ok(SystemClipboard)
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl OfferedFormats for SystemClipboard {
+    fn formats(&self) -> Vec<String> {
+        let Ok(_clip) = clipboard_win::Clipboard::new_attempts(10) else {
+            return vec![];
+        };
+
+        clipboard_win::EnumFormats::new()
+            .filter_map(clipboard_win::raw::format_name_big)
+            .collect()
+    }
+
+    fn get(&self, format: &str) -> Option<Vec<u8>> {
+        let _clip = clipboard_win::Clipboard::new_attempts(10).ok()?;
+        let format = clipboard_win::register_format(format)?;
+        if !clipboard_win::is_format_avail(format.get()) {
+            return None;
+        }
+
+        let mut data = Vec::new();
+        clipboard_win::raw::get_vec(format.get(), &mut data).ok()?;
+
+        Some(data)
+    }
+}
+
+/// One connection for all the requests of a check
+#[cfg(target_os = "linux")]
+pub struct SystemClipboard {
+    selection: crate::x11::X11Selection,
+}
+
+#[cfg(target_os = "linux")]
+impl SystemClipboard {
+    pub fn open(selection: Selection) -> Result<Self, String> {
+        Ok(SystemClipboard {
+            selection: crate::x11::X11Selection::new(selection.x11_name())?,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl OfferedFormats for SystemClipboard {
+    fn formats(&self) -> Vec<String> {
+        self.selection.targets().unwrap_or_default()
+    }
+
+    fn get(&self, format: &str) -> Option<Vec<u8>> {
+        self.selection.convert(format).ok()
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub struct SystemClipboard;
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+impl SystemClipboard {
+    pub fn open(_selection: Selection) -> Result<Self, String> {
+        Ok(SystemClipboard)
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+impl OfferedFormats for SystemClipboard {
+    fn formats(&self) -> Vec<String> {
+        // TODO: add macos (org.nspasteboard.ConcealedType)
+        vec![]
+    }
+
+    fn get(&self, _format: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct FakeClipboard {
+        formats: HashMap<String, Vec<u8>>,
+    }
+
+    impl FakeClipboard {
+        fn offer(mut self, format: &str, data: &[u8]) -> Self {
+            self.formats.insert(format.to_string(), data.to_vec());
+            self
+        }
+    }
+
+    impl OfferedFormats for FakeClipboard {
+        fn formats(&self) -> Vec<String> {
+            self.formats.keys().cloned().collect()
+        }
+
+        fn get(&self, format: &str) -> Option<Vec<u8>> {
+            self.formats.get(format).cloned()
+        }
+    }
+
+    fn text() -> FakeClipboard {
+        FakeClipboard::default()
+            .offer("UTF8_STRING", b"hunter2")
+            .offer("TARGETS", b"")
+    }
+
+    #[test]
+    fn plain_copy_is_recorded() {
+        assert!(!is_secret(&text()));
+    }
+
+    #[test]
+    fn kde_secret_hint_is_skipped() {
+        assert!(is_secret(&text().offer(KDE_PASSWORD_MANAGER_HINT, b"secret")));
+    }
+
+    #[test]
+    fn kde_other_hint_is_recorded() {
+        assert!(!is_secret(&text().offer(KDE_PASSWORD_MANAGER_HINT, b"not-secret")));
+    }
+
+    #[test]
+    fn windows_exclude_hint_is_skipped() {
+        assert!(is_secret(&text().offer(EXCLUDE_FROM_MONITOR, b"")));
+        assert!(is_secret(&text().offer(CLIPBOARD_VIEWER_IGNORE, b"")));
+    }
+
+    #[test]
+    fn windows_history_hint_depends_on_value() {
+        assert!(is_secret(&text().offer(CAN_INCLUDE_IN_HISTORY, &0u32.to_le_bytes())));
+        assert!(!is_secret(&text().offer(CAN_INCLUDE_IN_HISTORY, &1u32.to_le_bytes())));
+    }
+
+    /// Needs an X server and `xclip`, see `x11::skip_without_display`
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn checks_hints_of_the_captured_selection() {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        // xclip forks and keeps serving the hint until someone else takes the selection
+        let mut xclip = Command::new("xclip")
+            .args(["-selection", "clipboard", "-t", KDE_PASSWORD_MANAGER_HINT])
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("xclip is not installed");
+        xclip.stdin.take().unwrap().write_all(b"secret").unwrap();
+        xclip.wait().unwrap();
+
+        assert!(should_skip_capture(Selection::Clipboard));
+        assert!(!should_skip_capture(Selection::Primary));
+    }
+}
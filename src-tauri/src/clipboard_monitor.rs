@@ -0,0 +1,310 @@
+use crate::clipboard::my_clipboard;
+use crate::processes::{feature_active, Feature};
+use std::thread;
+use std::thread::sleep;
+use std::time::Duration;
+
+/// Used when the clipboard listener window can't be created, reading the sequence number is cheap
+#[cfg(target_os = "windows")]
+const SEQUENCE_POLL_INTERVAL: u64 = 100;
+
+/// PRIMARY gets a new owner repeatedly while text is being selected with mouse, only the final selection is handled
+#[cfg(target_os = "linux")]
+const PRIMARY_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Used where owner change notifications aren't available, e.g. Wayland without XWayland
+#[cfg(not(target_os = "windows"))]
+const FALLBACK_POLL_INTERVAL: u64 = 1000;
+
+/// Watches clipboard owner changes and captures new contents
+pub fn run() {
+    let _ = thread::Builder::new()
+        .name("clipboard:monitor".to_string())
+        .spawn(|| {
+            my_clipboard::image::init_prev_image().unwrap();
+
+            if let Err(e) = watch(on_change) {
+                eprintln!("clipboard monitor: {}, falling back to polling", e);
+                poll(on_change);
+            }
+        });
+
+    #[cfg(target_os = "linux")]
+    let _ = thread::Builder::new()
+        .name("clipboard:primary_monitor".to_string())
+        .spawn(|| {
+            let on_select = || {
+                if feature_active(Feature::Clipboard) {
+                    my_clipboard::primary::on_select();
+                }
+            };
+
+            if let Err(e) = watch_selection("PRIMARY", PRIMARY_DEBOUNCE, on_select) {
+                eprintln!("primary selection monitor: {}", e);
+            }
+        });
+}
+
+fn on_change() {
+    if !feature_active(Feature::Clipboard) {
+        return;
+    }
+
+    // contents kept by cboard were captured when it took them
+    #[cfg(target_os = "linux")]
+    if crate::clipboard_persistence::is_owner() {
+        return;
+    }
+
+    // text, html or files, image when nothing else is offered
+    my_clipboard::text::on_copy();
+
+    // apps copying an image often offer its url or html too
+    if my_clipboard::has_text() && my_clipboard::has_image() {
+        my_clipboard::image::on_copy();
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        my_clipboard::primary::sync_from_clipboard();
+        crate::clipboard_persistence::persist();
+    }
+}
+
+/// Calls `on_change` periodically, repeated contents are dropped by `on_copy`
+#[cfg(not(target_os = "windows"))]
+fn poll(mut on_change: impl FnMut()) {
+    loop {
+        sleep(Duration::from_millis(FALLBACK_POLL_INTERVAL));
+
+        on_change();
+    }
+}
+
+/// Calls `on_change` every time clipboard sequence number changes
+#[cfg(target_os = "windows")]
+fn poll(mut on_change: impl FnMut()) {
+    let mut last = clipboard_win::raw::seq_num();
+
+    loop {
+        sleep(Duration::from_millis(SEQUENCE_POLL_INTERVAL));
+
+        let current = clipboard_win::raw::seq_num();
+        if current != last {
+            last = current;
+            on_change();
+        }
+    }
+}
+
+/// Calls `on_change` on every `WM_CLIPBOARDUPDATE` sent to a message-only listener window,
+/// blocks until the message loop ends
+#[cfg(target_os = "windows")]
+pub fn watch(mut on_change: impl FnMut()) -> Result<(), String> {
+    use std::io::Error;
+    use std::ptr;
+    use winapi::um::winuser::{
+        AddClipboardFormatListener, CreateWindowExW, DestroyWindow, GetMessageW,
+        RemoveClipboardFormatListener, HWND_MESSAGE, MSG, WM_CLIPBOARDUPDATE,
+    };
+
+    let class: Vec<u16> = "STATIC\0".encode_utf16().collect();
+
+    unsafe {
+        let hwnd = CreateWindowExW(
+            0,
+            class.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        if hwnd.is_null() {
+            return Err(format!(
+                "Couldn't create listener window: {}",
+                Error::last_os_error()
+            ));
+        }
+
+        if AddClipboardFormatListener(hwnd) == 0 {
+            let e = Error::last_os_error();
+            DestroyWindow(hwnd);
+            return Err(format!("Couldn't add clipboard listener: {}", e));
+        }
+
+        let mut msg: MSG = std::mem::zeroed();
+        let result = loop {
+            match GetMessageW(&mut msg, hwnd, 0, 0) {
+                -1 => break Err(format!("Couldn't get message: {}", Error::last_os_error())),
+                0 => break Ok(()),
+                _ if msg.message == WM_CLIPBOARDUPDATE => on_change(),
+                _ => {}
+            }
+        };
+
+        RemoveClipboardFormatListener(hwnd);
+        DestroyWindow(hwnd);
+
+        result
+    }
+}
+
+/// Calls `on_change` every time CLIPBOARD gets a new owner, blocks until connection fails
+#[cfg(target_os = "linux")]
+pub fn watch(on_change: impl FnMut()) -> Result<(), String> {
+    watch_selection("CLIPBOARD", Duration::ZERO, on_change)
+}
+
+/// Calls `on_change` when `selection` gets a new owner (XFixes notification) and keeps it for `debounce`,
+/// blocks until connection fails
+#[cfg(target_os = "linux")]
+pub fn watch_selection(
+    selection: &str,
+    debounce: Duration,
+    mut on_change: impl FnMut(),
+) -> Result<(), String> {
+    use crate::x11::X11Selection;
+    use std::time::Instant;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xfixes::{ConnectionExt, SelectionEventMask};
+    use x11rb::protocol::Event;
+
+    let selection = X11Selection::new(selection)?;
+    let conn = selection.conn();
+
+    conn.xfixes_query_version(5, 0)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    conn.xfixes_select_selection_input(
+        selection.window(),
+        selection.selection(),
+        SelectionEventMask::SET_SELECTION_OWNER,
+    )
+    .map_err(|e| e.to_string())?;
+    conn.flush().map_err(|e| e.to_string())?;
+
+    // time of the last owner change not handled yet
+    let mut pending: Option<Instant> = None;
+
+    loop {
+        let event = match pending {
+            None => Some(conn.wait_for_event().map_err(|e| e.to_string())?),
+            Some(_) => conn.poll_for_event().map_err(|e| e.to_string())?,
+        };
+
+        match event {
+            Some(Event::XfixesSelectionNotify(event)) if event.owner != x11rb::NONE => {
+                pending = Some(Instant::now());
+            }
+            Some(_) => {}
+            None => sleep(Duration::from_millis(10)),
+        }
+
+        if pending.is_some_and(|changed| changed.elapsed() >= debounce) {
+            pending = None;
+            on_change();
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn watch(_on_change: impl FnMut()) -> Result<(), String> {
+    Err("Clipboard change notifications aren't supported".to_string())
+}
+
+/// Need an X server and `xclip`: `xvfb-run cargo test clipboard_monitor`, skipped when `DISPLAY` is not set
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc::{self, Receiver};
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    fn start_watching() -> Receiver<()> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            watch(|| sender.send(()).unwrap()).unwrap();
+        });
+
+        // selection input must be selected before xclip takes ownership
+        sleep(Duration::from_millis(200));
+
+        receiver
+    }
+
+    /// xclip forks and keeps serving the selection until someone else takes it
+    fn xclip(selection: &str, text: &str) {
+        let mut child = Command::new("xclip")
+            .args(["-selection", selection])
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("xclip is not installed");
+
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn notifies_on_every_clipboard_owner_change() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let changes = start_watching();
+
+        xclip("clipboard", "first");
+        changes.recv_timeout(TIMEOUT).unwrap();
+
+        xclip("clipboard", "second");
+        changes.recv_timeout(TIMEOUT).unwrap();
+    }
+
+    #[test]
+    fn ignores_primary_selection() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let changes = start_watching();
+
+        xclip("primary", "selected text");
+        assert!(changes.recv_timeout(TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn debounces_primary_selection() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let (sender, changes) = mpsc::channel();
+        thread::spawn(move || {
+            let debounce = Duration::from_millis(500);
+            watch_selection("PRIMARY", debounce, || sender.send(()).unwrap()).unwrap();
+        });
+        sleep(Duration::from_millis(200));
+
+        // like a drag selection growing word by word
+        for text in ["some", "some selected", "some selected text"] {
+            xclip("primary", text);
+            sleep(Duration::from_millis(100));
+        }
+
+        changes.recv_timeout(TIMEOUT).unwrap();
+        assert!(changes.recv_timeout(TIMEOUT).is_err());
+    }
+}
@@ -0,0 +1,666 @@
+use crate::clipboard::{capture_suppressed, my_clipboard};
+use crate::clipboard_hints::{self, Selection};
+use crate::sensitive::{self, SensitivePolicy};
+use crate::settings::get_settings_instance;
+use crate::x11::X11Selection;
+use parking_lot::Mutex;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEvent, SelectionEventMask};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt, EventMask, PropMode, SelectionNotifyEvent,
+    SelectionRequestEvent, Window, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::CURRENT_TIME;
+
+/// Targets describing the selection rather than holding its contents
+const META_TARGETS: [&str; 5] = ["TARGETS", "MULTIPLE", "TIMESTAMP", "SAVE_TARGETS", "DELETE"];
+
+/// Contents of one target as the original owner gave them
+#[derive(Debug, Clone)]
+pub struct Target {
+    target: Atom,
+    kind: Atom,
+    format: u8,
+    data: Vec<u8>,
+}
+
+/// Contents read from an app right after it copied, served once that app exits
+struct Kept {
+    owner: Window,
+    targets: Vec<Target>,
+}
+
+/// Owns CLIPBOARD with its own window and serves the last captured contents from memory,
+/// so they're still there after the source app exits
+pub struct Persistence {
+    selection: X11Selection,
+    targets_atom: Atom,
+    contents: Mutex<Vec<Target>>,
+    kept: Mutex<Option<Kept>>,
+    /// last owner reported by XFixes, the one whose exit is reported next
+    last_owner: Mutex<Window>,
+}
+
+impl Persistence {
+    pub fn new() -> Result<Self, String> {
+        let selection = X11Selection::new("CLIPBOARD")?;
+        let targets_atom = selection.atom("TARGETS")?;
+
+        let conn = selection.conn();
+        conn.xfixes_query_version(5, 0)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+        conn.xfixes_select_selection_input(
+            selection.window(),
+            selection.selection(),
+            SelectionEventMask::SET_SELECTION_OWNER
+                | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+        )
+        .map_err(|e| e.to_string())?;
+        conn.flush().map_err(|e| e.to_string())?;
+
+        Ok(Persistence {
+            last_owner: Mutex::new(owner_of(&selection).unwrap_or(x11rb::NONE)),
+            selection,
+            targets_atom,
+            contents: Mutex::new(vec![]),
+            kept: Mutex::new(None),
+        })
+    }
+
+    pub fn is_owner(&self) -> bool {
+        self.selection
+            .conn()
+            .get_selection_owner(self.selection.selection())
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|reply| reply.owner == self.selection.window())
+    }
+
+    /// Bigger targets can't be written to a property with a single request
+    fn max_target_bytes(&self) -> usize {
+        self.selection.conn().maximum_request_bytes() - 1024
+    }
+
+    /// Reads every target the current owner offers. Targets transferred with INCR or too big to serve
+    /// are skipped, or fail the whole snapshot when it must be `complete`
+    pub fn snapshot(&self, source: &X11Selection, complete: bool) -> Result<Vec<Target>, String> {
+        let incr = source.atom("INCR")?;
+        let mut targets = vec![];
+
+        for name in source.targets()? {
+            if META_TARGETS.contains(&name.as_str()) {
+                continue;
+            }
+
+            let Ok(reply) = source.convert_raw(&name) else {
+                continue;
+            };
+            if reply.type_ == incr || reply.value.len() > self.max_target_bytes() {
+                if complete {
+                    return Err(format!("{} is too big to keep", name));
+                }
+                continue;
+            }
+
+            targets.push(Target {
+                target: source.atom(&name)?,
+                kind: reply.type_,
+                format: reply.format,
+                data: reply.value,
+            });
+        }
+
+        Ok(targets)
+    }
+
+    /// Becomes the selection owner serving `targets`
+    pub fn take(&self, targets: Vec<Target>) -> Result<(), String> {
+        if targets.is_empty() {
+            return Err("Owner offered nothing to keep".to_string());
+        }
+
+        *self.contents.lock() = targets;
+
+        let conn = self.selection.conn();
+        conn.set_selection_owner(
+            self.selection.window(),
+            self.selection.selection(),
+            CURRENT_TIME,
+        )
+        .map_err(|e| e.to_string())?;
+        conn.flush().map_err(|e| e.to_string())?;
+
+        if !self.is_owner() {
+            self.contents.lock().clear();
+            return Err("Couldn't become selection owner".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Remembers `targets` read from `owner`, they're served once it exits.
+    /// The source app stays the owner until then, e.g. a cut in a file manager is still a move
+    pub fn keep(&self, owner: Window, targets: Vec<Target>) -> Result<(), String> {
+        if targets.is_empty() {
+            return Err("Owner offered nothing to keep".to_string());
+        }
+
+        *self.kept.lock() = Some(Kept { owner, targets });
+
+        // the owner could exit while it was being read
+        if owner_of(&self.selection) != Some(owner) {
+            self.take_kept(owner)?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets kept contents, e.g. when the next copy can't be kept
+    pub fn forget(&self) {
+        self.kept.lock().take();
+    }
+
+    /// Serves contents kept from `owner` after it exited
+    fn take_kept(&self, owner: Window) -> Result<(), String> {
+        let kept = {
+            let mut kept = self.kept.lock();
+            if !kept.as_ref().is_some_and(|k| k.owner == owner) {
+                return Ok(());
+            }
+            kept.take()
+        };
+
+        match kept {
+            // somebody else copied after the owner exited
+            Some(_) if owner_of(&self.selection).is_some() => Ok(()),
+            Some(kept) => self.take(kept.targets),
+            None => Ok(()),
+        }
+    }
+
+    /// Becomes the selection owner serving `targets` given by name, each as 8-bit data of its own type
+    pub fn offer(&self, targets: &[(&str, &[u8])]) -> Result<(), String> {
+        let targets = targets
+            .iter()
+            .map(|(name, data)| {
+                let atom = self.selection.atom(name)?;
+
+                Ok(Target {
+                    target: atom,
+                    kind: atom,
+                    format: 8,
+                    data: data.to_vec(),
+                })
+            })
+            .collect::<Result<Vec<Target>, String>>()?;
+
+        self.take(targets)
+    }
+
+    /// Answers requests of other apps, blocks until connection fails
+    pub fn serve(&self) -> Result<(), String> {
+        loop {
+            let event = self
+                .selection
+                .conn()
+                .wait_for_event()
+                .map_err(|e| e.to_string())?;
+
+            match event {
+                Event::SelectionRequest(request) => self.answer(request)?,
+                // another app copied something, it's captured by the monitor
+                Event::SelectionClear(event) if event.selection == self.selection.selection() => {
+                    self.contents.lock().clear();
+                }
+                Event::XfixesSelectionNotify(event)
+                    if event.selection == self.selection.selection() =>
+                {
+                    if event.subtype == SelectionEvent::SET_SELECTION_OWNER {
+                        *self.last_owner.lock() = event.owner;
+                        continue;
+                    }
+
+                    // owner window was destroyed or its app exited
+                    let owner = *self.last_owner.lock();
+                    if let Err(e) = self.take_kept(owner) {
+                        eprintln!("clipboard persistence: {}", e);
+                    }
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    fn answer(&self, request: SelectionRequestEvent) -> Result<(), String> {
+        let conn = self.selection.conn();
+
+        // obsolete clients leave property empty and expect the target name to be used
+        let mut property = if request.property == u32::from(AtomEnum::NONE) {
+            request.target
+        } else {
+            request.property
+        };
+
+        let contents = self.contents.lock();
+        if request.target == self.targets_atom {
+            let mut atoms: Vec<Atom> = contents.iter().map(|t| t.target).collect();
+            atoms.push(self.targets_atom);
+
+            conn.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &atoms,
+            )
+            .map_err(|e| e.to_string())?;
+        } else if let Some(target) = contents.iter().find(|t| t.target == request.target) {
+            let length = target.data.len() as u32 / u32::from(target.format / 8).max(1);
+
+            conn.change_property(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                target.kind,
+                target.format,
+                length,
+                &target.data,
+            )
+            .map_err(|e| e.to_string())?;
+        } else {
+            property = AtomEnum::NONE.into();
+        }
+        drop(contents);
+
+        let notify = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: request.time,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property,
+        };
+        conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify)
+            .map_err(|e| e.to_string())?;
+        conn.flush().map_err(|e| e.to_string())
+    }
+}
+
+static PERSISTENCE: OnceLock<Arc<Persistence>> = OnceLock::new();
+
+/// Starts serving kept contents, nothing is kept if there is no X server
+pub fn run() {
+    let persistence = match Persistence::new() {
+        Ok(persistence) => Arc::new(persistence),
+        Err(e) => {
+            eprintln!("clipboard persistence: {}", e);
+            return;
+        }
+    };
+
+    if PERSISTENCE.set(persistence.clone()).is_err() {
+        return;
+    }
+
+    let _ = thread::Builder::new()
+        .name("clipboard:persistence".to_string())
+        .spawn(move || {
+            if let Err(e) = persistence.serve() {
+                eprintln!("clipboard persistence: {}", e);
+            }
+        });
+}
+
+/// Whether current clipboard contents are the ones kept by cboard
+pub fn is_owner() -> bool {
+    PERSISTENCE.get().is_some_and(|p| p.is_owner())
+}
+
+/// Serves `targets` as new clipboard contents, for formats arboard can't set (html with rtf, file lists)
+pub fn offer(targets: &[(&str, &[u8])]) -> Result<(), String> {
+    PERSISTENCE
+        .get()
+        .ok_or("Clipboard isn't served, no X server?")?
+        .offer(targets)
+}
+
+/// Every target of current clipboard contents, empty if nobody owns the clipboard
+pub fn snapshot() -> Result<Vec<Target>, String> {
+    let persistence = PERSISTENCE
+        .get()
+        .ok_or("Clipboard isn't served, no X server?")?;
+
+    match X11Selection::new("CLIPBOARD") {
+        Ok(source) if owner_of(&source).is_some() => persistence.snapshot(&source, false),
+        Ok(_) => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
+
+/// Serves contents taken with `snapshot` again
+pub fn restore(targets: Vec<Target>) -> Result<(), String> {
+    PERSISTENCE
+        .get()
+        .ok_or("Clipboard isn't served, no X server?")?
+        .take(targets)
+}
+
+fn owner_of(selection: &X11Selection) -> Option<Window> {
+    selection
+        .conn()
+        .get_selection_owner(selection.selection())
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .map(|reply| reply.owner)
+        .filter(|owner| *owner != x11rb::NONE)
+}
+
+/// Reads current clipboard contents, they're served after the source app exits.
+/// Nothing is kept unless every target could be read, a partial copy would replace the real one
+pub fn persist() {
+    let Some(persistence) = PERSISTENCE.get() else {
+        return;
+    };
+
+    if persistence.is_owner() {
+        return;
+    }
+
+    // contents of the previous copy must not come back when this one exits
+    persistence.forget();
+
+    if !get_settings_instance().lock().keep_clipboard_after_exit
+        || capture_suppressed()
+        || clipboard_hints::should_skip_capture(Selection::Clipboard)
+    {
+        return;
+    }
+
+    // password managers clear what they copied, this only works while they own the clipboard
+    if let Ok(text) = my_clipboard::text::get() {
+        let kind = sensitive::detect(&text);
+        if kind.is_some_and(|k| sensitive::policy_for(k) != SensitivePolicy::Store) {
+            return;
+        }
+    }
+
+    // pastes from history change clipboard under this lock too
+    let clipboard = my_clipboard::get_instance();
+    let _clipboard = clipboard.lock();
+
+    let result = X11Selection::new("CLIPBOARD").and_then(|source| {
+        let owner = owner_of(&source).ok_or("Nobody owns the clipboard")?;
+        let targets = persistence.snapshot(&source, true)?;
+
+        persistence.keep(owner, targets)
+    });
+
+    if let Err(e) = result {
+        eprintln!("clipboard persistence: {}", e);
+    }
+}
+
+/// Need an X server and `xclip`: `xvfb-run cargo test clipboard_persistence`, skipped when `DISPLAY` is not set
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::process::{Child, Command, Stdio};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    fn start() -> Arc<Persistence> {
+        let persistence = Arc::new(Persistence::new().unwrap());
+
+        let server = persistence.clone();
+        thread::spawn(move || server.serve().unwrap());
+
+        persistence
+    }
+
+    /// Runs xclip and waits for the owner to fork, the forked process exits when it loses ownership
+    fn xclip_copy(target: &str, text: &str) {
+        let mut child = Command::new("xclip")
+            .args(["-selection", "clipboard", "-t", target])
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("xclip is not installed");
+
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+        child.wait().unwrap();
+        sleep(Duration::from_millis(100));
+    }
+
+    fn xclip_paste(target: &str) -> String {
+        let output = Command::new("xclip")
+            .args(["-o", "-selection", "clipboard", "-t", target])
+            .output()
+            .unwrap();
+
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    fn keep_current(persistence: &Persistence) {
+        let source = X11Selection::new("CLIPBOARD").unwrap();
+        let targets = persistence.snapshot(&source, false).unwrap();
+        persistence.take(targets).unwrap();
+    }
+
+    /// Copies like an app which keeps running until it's killed
+    fn xclip_copy_in_foreground(text: &str) -> Child {
+        let mut child = Command::new("xclip")
+            .args(["-quiet", "-selection", "clipboard"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("xclip is not installed");
+
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+        sleep(Duration::from_millis(100));
+
+        child
+    }
+
+    fn keep_until_exit(persistence: &Persistence) -> Result<(), String> {
+        let source = X11Selection::new("CLIPBOARD").unwrap();
+        let owner = owner_of(&source).unwrap();
+        let targets = persistence.snapshot(&source, true)?;
+
+        persistence.keep(owner, targets)
+    }
+
+    #[test]
+    fn serves_contents_after_owner_exits() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+
+        xclip_copy("UTF8_STRING", "kept text");
+        keep_current(&persistence);
+
+        assert!(persistence.is_owner());
+        assert_eq!(xclip_paste("UTF8_STRING"), "kept text");
+    }
+
+    #[test]
+    fn serves_every_offered_target() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+
+        xclip_copy("text/html", "<b>kept</b>");
+        keep_current(&persistence);
+
+        assert!(xclip_paste("TARGETS").contains("text/html"));
+        assert_eq!(xclip_paste("text/html"), "<b>kept</b>");
+    }
+
+    #[test]
+    fn drops_contents_when_another_app_copies() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+
+        xclip_copy("UTF8_STRING", "old");
+        keep_current(&persistence);
+        xclip_copy("UTF8_STRING", "new");
+
+        assert!(!persistence.is_owner());
+        assert_eq!(xclip_paste("UTF8_STRING"), "new");
+    }
+
+    #[test]
+    fn serves_offered_targets() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+        persistence
+            .offer(&[
+                ("text/html", b"<i>offered</i>"),
+                ("UTF8_STRING", b"offered"),
+            ])
+            .unwrap();
+
+        assert!(persistence.is_owner());
+        assert_eq!(xclip_paste("text/html"), "<i>offered</i>");
+        assert_eq!(xclip_paste("UTF8_STRING"), "offered");
+    }
+
+    #[test]
+    fn reads_html_and_rtf_of_another_app() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+        persistence
+            .offer(&[
+                ("text/html", b"<b>rich</b>"),
+                ("application/rtf", b"{\\rtf1 rich}"),
+            ])
+            .unwrap();
+
+        let (html, rtf) = my_clipboard::html::get().unwrap();
+        assert_eq!(html, "<b>rich</b>");
+        assert_eq!(rtf.as_deref(), Some("{\\rtf1 rich}"));
+
+        xclip_copy("UTF8_STRING", "plain");
+        assert!(my_clipboard::html::get().is_none());
+    }
+
+    #[test]
+    fn reads_files_of_another_app() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+        persistence
+            .offer(&[("text/uri-list", b"file:///tmp/a%20b.txt\r\n")])
+            .unwrap();
+
+        assert_eq!(
+            my_clipboard::files::get(),
+            Some(vec!["/tmp/a b.txt".to_string()])
+        );
+    }
+
+    #[test]
+    fn restores_every_target_of_a_snapshot() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+        persistence
+            .offer(&[("text/html", b"<b>before</b>"), ("UTF8_STRING", b"before")])
+            .unwrap();
+
+        let source = X11Selection::new("CLIPBOARD").unwrap();
+        let targets = persistence.snapshot(&source, false).unwrap();
+        assert_eq!(targets.len(), 2);
+
+        xclip_copy("UTF8_STRING", "pasted from history");
+        persistence.take(targets).unwrap();
+
+        assert_eq!(xclip_paste("text/html"), "<b>before</b>");
+        assert_eq!(xclip_paste("UTF8_STRING"), "before");
+    }
+
+    #[test]
+    fn leaves_ownership_to_a_running_app() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+
+        xclip_copy("UTF8_STRING", "still served by its app");
+        keep_until_exit(&persistence).unwrap();
+
+        assert!(!persistence.is_owner());
+        assert_eq!(xclip_paste("UTF8_STRING"), "still served by its app");
+    }
+
+    #[test]
+    fn takes_ownership_when_the_app_exits() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+
+        let mut app = xclip_copy_in_foreground("served after exit");
+        keep_until_exit(&persistence).unwrap();
+        assert!(!persistence.is_owner());
+
+        app.kill().unwrap();
+        app.wait().unwrap();
+        sleep(Duration::from_millis(200));
+
+        assert!(persistence.is_owner());
+        assert_eq!(xclip_paste("UTF8_STRING"), "served after exit");
+    }
+
+    #[test]
+    fn keeps_nothing_when_a_target_is_too_big() {
+        if crate::x11::skip_without_display() {
+            return;
+        }
+
+        let persistence = start();
+
+        // xclip sends it with INCR
+        xclip_copy("UTF8_STRING", &"x".repeat(4 * 1024 * 1024));
+        assert!(keep_until_exit(&persistence).is_err());
+
+        assert!(!persistence.is_owner());
+        assert_eq!(xclip_paste("UTF8_STRING").len(), 4 * 1024 * 1024);
+    }
+}
@@ -0,0 +1,543 @@
+use crate::clipboard::FileTypes;
+use crate::crypto;
+use crate::history::{self, HistoryItem};
+use serde::{Deserialize, Serialize};
+
+/// Text which starts like this is treated as a link, `scheme://` with any other scheme counts too
+const URL_PREFIXES: [&str; 2] = ["www.", "mailto:"];
+
+/// Markers of a language, each one found in the text adds a point.
+/// Typescript has only markers plain javascript can't have, javascript with any of them is typescript
+const LANGUAGES: [(&str, &[&str]); 11] = [
+    (
+        "rust",
+        &[
+            "fn ",
+            "let mut ",
+            "impl ",
+            "pub fn ",
+            "use std::",
+            "&mut ",
+            "println!",
+            "::new(",
+        ],
+    ),
+    (
+        "python",
+        &[
+            "def ",
+            "elif ",
+            "self.",
+            "print(",
+            "__init__",
+            "if __name__",
+            "from typing import",
+        ],
+    ),
+    (
+        "typescript",
+        &[
+            "interface ",
+            ": string",
+            ": number",
+            ": boolean",
+            "): void",
+            "?: ",
+            "export type ",
+            "as const",
+            "<T>",
+            "readonly ",
+        ],
+    ),
+    (
+        "javascript",
+        &[
+            "function ",
+            "const ",
+            "=> ",
+            "console.log",
+            "document.",
+            "require(",
+            "export ",
+        ],
+    ),
+    (
+        "sql",
+        &[
+            "SELECT ",
+            "FROM ",
+            "WHERE ",
+            "INSERT INTO ",
+            "UPDATE ",
+            "CREATE TABLE ",
+            "JOIN ",
+        ],
+    ),
+    (
+        "html",
+        &[
+            "<!DOCTYPE",
+            "<html",
+            "<div",
+            "</div>",
+            "<span",
+            "<p>",
+            "href=\"",
+        ],
+    ),
+    (
+        "shell",
+        &[
+            "#!/bin/", "echo ", "sudo ", "export ", "then\n", "fi\n", "$(",
+        ],
+    ),
+    ("c", &["#include", "int main(", "printf(", "malloc("]),
+    (
+        "java",
+        &[
+            "public class ",
+            "System.out",
+            "public static void",
+            "import java.",
+            "private ",
+        ],
+    ),
+    (
+        "csharp",
+        &[
+            "using System",
+            "namespace ",
+            "Console.WriteLine",
+            "public class ",
+        ],
+    ),
+    ("go", &["func ", "package ", ":= ", "fmt.", "go func"]),
+];
+
+/// What captured text looks like, lets UI show icons and actions and search filter by it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ContentKind {
+    Url,
+    Email,
+    /// Windows or unix file path
+    Path,
+    /// `#rgb`, `#rrggbbaa` or `rgb()` / `rgba()`, `hex` is normalized to `#rrggbb` or `#rrggbbaa`
+    Color {
+        hex: String,
+    },
+    /// object or array
+    Json,
+    Number,
+    Phone,
+    /// `language` is a guess, None when it's code in general
+    Code {
+        language: Option<String>,
+    },
+    /// plain prose, anything else
+    Text,
+}
+
+impl ContentKind {
+    /// Used by the search filter
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentKind::Url => "url",
+            ContentKind::Email => "email",
+            ContentKind::Path => "path",
+            ContentKind::Color { .. } => "color",
+            ContentKind::Json => "json",
+            ContentKind::Number => "number",
+            ContentKind::Phone => "phone",
+            ContentKind::Code { .. } => "code",
+            ContentKind::Text => "text",
+        }
+    }
+}
+
+pub fn classify(text: &str) -> ContentKind {
+    let text = text.trim();
+
+    if is_json(text) {
+        return ContentKind::Json;
+    }
+
+    if !text.contains('\n') {
+        if let Some(hex) = color_hex(text) {
+            return ContentKind::Color { hex };
+        }
+
+        // order matters, e.g. `mailto:` links are emails and `+1 555 0100` isn't a number
+        if is_email(text) {
+            return ContentKind::Email;
+        }
+        if is_url(text) {
+            return ContentKind::Url;
+        }
+        if is_path(text) {
+            return ContentKind::Path;
+        }
+        if is_phone(text) {
+            return ContentKind::Phone;
+        }
+        if is_number(text) {
+            return ContentKind::Number;
+        }
+    }
+
+    match code_language(text) {
+        Some(language) => ContentKind::Code { language },
+        None => ContentKind::Text,
+    }
+}
+
+/// Kind of a text item, items captured before classification existed are classified and updated on first use
+pub fn of_item(item: &HistoryItem) -> Option<ContentKind> {
+    if item.kind.is_some() || item.extension() != FileTypes::TXT {
+        return item.kind.clone();
+    }
+
+    // unreadable while encrypted history is locked, tried again next time
+    let kind = classify(&crypto::read_to_string(&item.path()).ok()?);
+    history::update_item(&item.folder, &item.name, |stored| {
+        stored.kind = Some(kind.clone())
+    });
+
+    Some(kind)
+}
+
+fn is_json(text: &str) -> bool {
+    (text.starts_with('{') || text.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+fn color_hex(text: &str) -> Option<String> {
+    if let Some(digits) = text.strip_prefix('#') {
+        if !matches!(digits.len(), 3 | 4 | 6 | 8) || !digits.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return None;
+        }
+
+        // `#123` is rather an issue or a ticket number
+        if digits.len() <= 4 && digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let digits = digits.to_ascii_lowercase();
+        // `#abc` is `#aabbcc`
+        let digits = match digits.len() {
+            3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+            _ => digits,
+        };
+
+        return Some(format!("#{}", digits));
+    }
+
+    let lower = text.to_ascii_lowercase();
+    let arguments = lower
+        .strip_prefix("rgba(")
+        .or_else(|| lower.strip_prefix("rgb("))?
+        .strip_suffix(')')?;
+    let parts: Vec<&str> = arguments
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+        .collect();
+    if !matches!(parts.len(), 3 | 4) {
+        return None;
+    }
+
+    let mut hex = String::from("#");
+    for part in &parts[..3] {
+        hex.push_str(&format!("{:02x}", part.parse::<u8>().ok()?));
+    }
+
+    if let Some(alpha) = parts.get(3) {
+        let alpha = match alpha.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+            None => alpha.parse::<f32>().ok()?,
+        };
+        if !(0.0..=1.0).contains(&alpha) {
+            return None;
+        }
+
+        hex.push_str(&format!("{:02x}", (alpha * 255.0).round() as u8));
+    }
+
+    Some(hex)
+}
+
+fn is_url(text: &str) -> bool {
+    if text.chars().any(char::is_whitespace) {
+        return false;
+    }
+
+    let lower = text.to_ascii_lowercase();
+    if URL_PREFIXES
+        .iter()
+        .any(|p| lower.starts_with(p) && lower.len() > p.len())
+    {
+        return true;
+    }
+
+    match lower.split_once("://") {
+        Some((scheme, rest)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                && !rest.is_empty()
+        }
+        None => false,
+    }
+}
+
+fn is_email(text: &str) -> bool {
+    let address = text.strip_prefix("mailto:").unwrap_or(text);
+    let Some((local, domain)) = address.split_once('@') else {
+        return false;
+    };
+
+    let local_valid = !local.is_empty()
+        && !local
+            .chars()
+            .any(|c| c.is_whitespace() || "()<>,;:\"[]@".contains(c));
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_valid = labels.len() > 1
+        && labels
+            .iter()
+            .all(|l| !l.is_empty() && l.chars().all(|c| c.is_alphanumeric() || c == '-'))
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() > 1 && tld.chars().all(char::is_alphabetic));
+
+    local_valid && domain_valid
+}
+
+fn is_path(text: &str) -> bool {
+    let bytes = text.as_bytes();
+
+    let windows = (bytes.len() > 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/'))
+        || (text.starts_with("\\\\") && text.len() > 2);
+
+    let unix = ["/", "~/", "./", "../"].iter().any(|prefix| {
+        text.strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with(|c: char| !c.is_whitespace() && c != '/'))
+    });
+
+    (windows || unix) && !text.contains("://") && !text.chars().any(|c| "<>|\"".contains(c))
+}
+
+/// Digits with the usual separators, short numbers need a `+` country code so dates and ids don't pass
+fn is_phone(text: &str) -> bool {
+    let international = text.starts_with('+');
+    let body = text.strip_prefix('+').unwrap_or(text);
+    if !body
+        .chars()
+        .all(|c| c.is_ascii_digit() || " -().".contains(c))
+    {
+        return false;
+    }
+
+    let digits = body.chars().filter(char::is_ascii_digit).count();
+    let min_digits = if international { 7 } else { 10 };
+    let separated = body.chars().any(|c| " -()".contains(c));
+
+    (min_digits..=15).contains(&digits) && (international || separated)
+}
+
+/// Integers and decimals, `_` and `,` group digits
+fn is_number(text: &str) -> bool {
+    text.chars().any(|c| c.is_ascii_digit())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || "+-.,_eE".contains(c))
+        && text.replace(['_', ','], "").parse::<f64>().is_ok()
+}
+
+fn markers_of(language: &str) -> &'static [&'static str] {
+    LANGUAGES
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map_or(&[], |(_, markers)| markers)
+}
+
+/// Some(None) for code of an unknown language, None for prose
+fn code_language(text: &str) -> Option<Option<String>> {
+    let (language, score) = LANGUAGES
+        .iter()
+        .map(|(name, markers)| (*name, markers.iter().filter(|m| text.contains(*m)).count()))
+        .fold(
+            ("", 0),
+            |best, current| if current.1 > best.1 { current } else { best },
+        );
+
+    if score >= 2 {
+        let typed =
+            language == "javascript" && markers_of("typescript").iter().any(|m| text.contains(m));
+        let language = if typed { "typescript" } else { language };
+
+        return Some(Some(language.to_string()));
+    }
+
+    // most lines of code end with punctuation, sentences end with a dot
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let structured = lines
+        .iter()
+        .filter(|l| l.ends_with([';', '{', '}', '(', ')']) || l.starts_with("//"))
+        .count();
+
+    (lines.len() > 1 && structured * 2 >= lines.len()).then_some(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(text: &str) -> &'static str {
+        classify(text).name()
+    }
+
+    #[test]
+    fn detects_links_and_addresses() {
+        assert_eq!(kind("https://example.com/a?b=c"), "url");
+        assert_eq!(kind("  www.example.com\n"), "url");
+        assert_eq!(kind("ssh://git@host/repo"), "url");
+        assert_eq!(kind("see https://example.com"), "text");
+
+        assert_eq!(kind("someone@example.com"), "email");
+        assert_eq!(kind("mailto:first.last+tag@mail.example.org"), "email");
+        assert_eq!(kind("someone@localhost"), "text");
+
+        assert_eq!(kind("C:\\Users\\me\\My Documents"), "path");
+        assert_eq!(kind("\\\\server\\share"), "path");
+        assert_eq!(kind("/usr/local/bin"), "path");
+        assert_eq!(kind("~/.config/cboard"), "path");
+        assert_eq!(kind("/ not a path"), "text");
+    }
+
+    #[test]
+    fn normalizes_colors() {
+        let hex = |text: &str| match classify(text) {
+            ContentKind::Color { hex } => hex,
+            other => panic!("{:?} is not a color", other),
+        };
+
+        assert_eq!(hex("#ABC"), "#aabbcc");
+        assert_eq!(hex("#11223344"), "#11223344");
+        assert_eq!(hex("rgb(255, 0, 16)"), "#ff0010");
+        assert_eq!(hex("rgba(0,0,0,0.5)"), "#00000080");
+        assert_eq!(hex("rgb(0 128 255 / 100%)"), "#0080ffff");
+
+        assert_eq!(hex("#1a2"), "#11aa22");
+        assert_eq!(hex("#000000"), "#000000");
+
+        assert_eq!(kind("#12345"), "text");
+        assert_eq!(kind("#123"), "text");
+        assert_eq!(kind("#1234"), "text");
+        assert_eq!(kind("rgb(256, 0, 0)"), "text");
+    }
+
+    #[test]
+    fn tells_numbers_from_phones() {
+        assert_eq!(kind("42"), "number");
+        assert_eq!(kind("-3.14"), "number");
+        assert_eq!(kind("1,000,000"), "number");
+        assert_eq!(kind("6.02e23"), "number");
+        assert_eq!(kind("1.2.3"), "text");
+
+        assert_eq!(kind("+1 555 010 0199"), "phone");
+        assert_eq!(kind("+442079460958"), "phone");
+        assert_eq!(kind("(555) 010-0199"), "phone");
+        assert_eq!(kind("2024-03-07"), "text");
+    }
+
+    #[test]
+    fn detects_json() {
+        assert_eq!(kind("{\"a\": [1, 2]}"), "json");
+        assert_eq!(kind("[\n  1,\n  2\n]"), "json");
+        assert_eq!(kind("{not json}"), "text");
+    }
+
+    #[test]
+    fn guesses_code_language() {
+        let language = |text: &str| match classify(text) {
+            ContentKind::Code { language } => language,
+            other => panic!("{:?} is not code", other),
+        };
+
+        assert_eq!(
+            language("pub fn main() {\n    let mut x = 1;\n}").as_deref(),
+            Some("rust")
+        );
+        assert_eq!(
+            language("def run(self):\n    print(self.name)").as_deref(),
+            Some("python")
+        );
+        assert_eq!(
+            language("SELECT id FROM users WHERE active = 1").as_deref(),
+            Some("sql")
+        );
+        assert_eq!(
+            language("interface User {\n  name: string;\n}").as_deref(),
+            Some("typescript")
+        );
+        assert_eq!(language("x = y;\nfoo(x);\n}"), None);
+    }
+
+    #[test]
+    fn tells_javascript_from_typescript() {
+        let language = |text: &str| match classify(text) {
+            ContentKind::Code { language } => language,
+            other => panic!("{:?} is not code", other),
+        };
+
+        assert_eq!(
+            language("const add = (a, b) => a + b;\nconsole.log(add(1, 2));").as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(
+            language("export const double = (x) => x * 2;").as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(
+            language("const add = (a: number, b: number) => a + b;\nconsole.log(add(1, 2));")
+                .as_deref(),
+            Some("typescript")
+        );
+        assert_eq!(
+            language("export type Id = string;\nconst ids = [] as const;").as_deref(),
+            Some("typescript")
+        );
+        assert_eq!(
+            language(
+                "function first<T>(items: T[]) {\n  const item = items[0];\n  return item;\n}"
+            )
+            .as_deref(),
+            Some("typescript")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_prose() {
+        assert_eq!(kind("Meeting moved to Friday."), "text");
+        assert_eq!(
+            kind("First line of a letter.\nSecond line, still prose."),
+            "text"
+        );
+        assert_eq!(
+            kind("Please import the report by Friday.\nNone of it is urgent."),
+            "text"
+        );
+        assert_eq!(kind("The field is NULL when None was given."), "text");
+        assert_eq!(kind(""), "text");
+    }
+}
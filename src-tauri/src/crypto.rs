@@ -0,0 +1,660 @@
+use crate::filesys::{read_json_data, write_json_data, Payload, FILENAME_ENCRYPTION};
+use crate::helpers::{app_data_dir, get_tauri_handle};
+use crate::history::{self, content_hash, get_history_instance, save_history};
+use crate::thumbnails;
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tauri::Manager;
+
+/// Encrypted files start with it, so plaintext files left from before encryption are still readable
+const MAGIC: &[u8] = b"CBENC1";
+const NONCE_LENGTH: usize = 12;
+/// Known text encrypted with the key, used to check a passphrase on unlock
+const CHECK_TEXT: &[u8] = b"cboard";
+
+const KEYRING_SERVICE: &str = "cboard";
+const KEYRING_USER: &str = "history-key";
+
+pub type EncryptionKey = [u8; 32];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    Passphrase,
+    Keyring,
+}
+
+/// Direction of a rewrite of every stored file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rewrite {
+    Encrypt,
+    Decrypt,
+}
+
+/// Stored in `encryption.json`, never contains the key itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    pub key_source: KeySource,
+    /// base64, used only with passphrase
+    pub salt: String,
+    /// base64 of `CHECK_TEXT` encrypted with the key
+    pub check: String,
+    /// set while files are rewritten, an interrupted rewrite is finished on unlock
+    #[serde(default)]
+    pub rewriting: Option<Rewrite>,
+}
+
+#[derive(Debug, Serialize)]
+struct EncryptionStatus {
+    enabled: bool,
+    unlocked: bool,
+    key_source: Option<KeySource>,
+}
+
+pub static ENCRYPTION_CONFIG: OnceLock<Arc<Mutex<Option<EncryptionConfig>>>> = OnceLock::new();
+
+/// Key of the unlocked history, None while locked
+pub static ENCRYPTION_KEY: OnceLock<Arc<Mutex<Option<EncryptionKey>>>> = OnceLock::new();
+
+fn get_config_instance() -> Arc<Mutex<Option<EncryptionConfig>>> {
+    ENCRYPTION_CONFIG
+        .get_or_init(|| Arc::new(Mutex::new(None)))
+        .clone()
+}
+
+fn get_key_instance() -> Arc<Mutex<Option<EncryptionKey>>> {
+    ENCRYPTION_KEY
+        .get_or_init(|| Arc::new(Mutex::new(None)))
+        .clone()
+}
+
+pub fn is_enabled() -> bool {
+    get_config_instance().lock().is_some()
+}
+
+pub fn is_unlocked() -> bool {
+    get_key_instance().lock().is_some()
+}
+
+/// Key used to hash contents of encrypted items, so the index doesn't reveal plaintext hashes
+pub fn hash_key() -> Option<EncryptionKey> {
+    if !is_enabled() {
+        return None;
+    }
+
+    let key = (*get_key_instance().lock())?;
+    let mut hash_key = [0u8; 32];
+    hash_key.copy_from_slice(&Sha256::digest([b"cboard-hash".as_slice(), &key].concat()));
+
+    Some(hash_key)
+}
+
+/// Reads encryption config on startup, history protected by the OS keyring is unlocked right away
+pub fn load_encryption() {
+    let Ok(config) = read_json_data::<EncryptionConfig>(FILENAME_ENCRYPTION) else {
+        return;
+    };
+
+    let key_source = config.key_source;
+    *get_config_instance().lock() = Some(config);
+
+    if key_source == KeySource::Keyring {
+        if let Err(e) = unlock(None) {
+            eprintln!("Couldn't unlock history from keyring: {}", e);
+        }
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<EncryptionKey, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+
+    Ok(key)
+}
+
+fn keyring_key(create: bool) -> Result<EncryptionKey, String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(|e| e.to_string())?;
+
+    match entry.get_password() {
+        Ok(encoded) => {
+            let bytes = BASE64.decode(encoded).map_err(|e| e.to_string())?;
+            bytes
+                .try_into()
+                .map_err(|_| "Invalid key in keyring".to_string())
+        }
+        Err(keyring::Error::NoEntry) if create => {
+            let key: EncryptionKey = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+            entry
+                .set_password(&BASE64.encode(key))
+                .map_err(|e| e.to_string())?;
+
+            Ok(key)
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn encrypt_with(key: &EncryptionKey, data: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, data).map_err(|e| e.to_string())?;
+
+    Ok([MAGIC, nonce.as_slice(), &ciphertext].concat())
+}
+
+fn decrypt_with(key: &EncryptionKey, data: &[u8]) -> Result<Vec<u8>, String> {
+    if !is_encrypted(data) || data.len() < MAGIC.len() + NONCE_LENGTH {
+        return Err("Data is not encrypted".to_string());
+    }
+
+    let (nonce, ciphertext) = data[MAGIC.len()..].split_at(NONCE_LENGTH);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Couldn't decrypt data, wrong key?".to_string())
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn current_key() -> Result<EncryptionKey, String> {
+    (*get_key_instance().lock()).ok_or("History is locked".to_string())
+}
+
+/// Reads a stored item file, decrypting it if needed
+pub fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+
+    decrypt_with(&current_key()?, &data)
+}
+
+pub fn read_to_string(path: &Path) -> Result<String, String> {
+    String::from_utf8(read_file(path)?).map_err(|e| e.to_string())
+}
+
+/// Writes a stored item file, encrypting it when encryption is enabled
+pub fn write_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    let data = if is_enabled() {
+        encrypt_with(&current_key()?, data)?
+    } else {
+        data.to_vec()
+    };
+
+    fs::write(path, data).map_err(|e| e.to_string())
+}
+
+fn config_path() -> PathBuf {
+    app_data_dir()
+        .join(crate::filesys::FOLDER_DATA)
+        .join(FILENAME_ENCRYPTION)
+}
+
+fn new_config(
+    key_source: KeySource,
+    salt: &[u8],
+    key: &EncryptionKey,
+) -> Result<EncryptionConfig, String> {
+    Ok(EncryptionConfig {
+        key_source,
+        salt: BASE64.encode(salt),
+        check: BASE64.encode(encrypt_with(key, CHECK_TEXT)?),
+        rewriting: None,
+    })
+}
+
+/// Key for `config`, checked against the known text
+fn unlock_key(
+    config: &EncryptionConfig,
+    passphrase: Option<String>,
+) -> Result<EncryptionKey, String> {
+    let key = match (config.key_source, passphrase) {
+        (KeySource::Passphrase, Some(passphrase)) => {
+            let salt = BASE64.decode(&config.salt).map_err(|e| e.to_string())?;
+            derive_key(&passphrase, &salt)?
+        }
+        (KeySource::Passphrase, None) => return Err("Passphrase is required".to_string()),
+        (KeySource::Keyring, _) => keyring_key(false)?,
+    };
+
+    let check = BASE64.decode(&config.check).map_err(|e| e.to_string())?;
+    if decrypt_with(&key, &check).ok().as_deref() != Some(CHECK_TEXT) {
+        return Err("Wrong passphrase".to_string());
+    }
+
+    Ok(key)
+}
+
+fn unlock(passphrase: Option<String>) -> Result<(), String> {
+    let config = get_config_instance()
+        .lock()
+        .clone()
+        .ok_or("Encryption is not enabled")?;
+
+    let key = unlock_key(&config, passphrase)?;
+    *get_key_instance().lock() = Some(key);
+
+    // the index can't be read or written without the key
+    if history::is_loaded() {
+        save_history(&get_history_instance().lock());
+    } else {
+        history::load_history();
+    }
+
+    if let Some(rewrite) = config.rewriting {
+        if let Err(e) = finish_rewrite(key, rewrite) {
+            eprintln!("Couldn't finish rewriting history: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Temp file next to `file`, swapped in once every file is rewritten
+fn rewrite_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".rewrite");
+
+    PathBuf::from(path)
+}
+
+/// Rewrites `files` encrypted with `key` or plain and returns their plain contents. Files already
+/// rewritten by an interrupted run are read either way, so running it again finishes the job.
+/// Everything is written to temp files first, so a failure leaves the originals untouched
+fn rewrite_files(
+    files: &[PathBuf],
+    key: &EncryptionKey,
+    encrypt: bool,
+) -> Result<Vec<Vec<u8>>, String> {
+    let mut contents = Vec::with_capacity(files.len());
+    let mut temps = Vec::with_capacity(files.len());
+
+    let mut write_temps = || -> Result<(), String> {
+        for file in files {
+            let data = fs::read(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            let data = match is_encrypted(&data) {
+                true => decrypt_with(key, &data)?,
+                false => data,
+            };
+
+            let rewritten = match encrypt {
+                true => encrypt_with(key, &data)?,
+                false => data.clone(),
+            };
+
+            let temp = rewrite_path(file);
+            fs::write(&temp, rewritten).map_err(|e| e.to_string())?;
+            temps.push(temp);
+            contents.push(data);
+        }
+
+        Ok(())
+    };
+
+    if let Err(e) = write_temps() {
+        for temp in &temps {
+            let _ = fs::remove_file(temp);
+        }
+        return Err(e);
+    }
+
+    for (file, temp) in files.iter().zip(&temps) {
+        fs::rename(temp, file).map_err(|e| e.to_string())?;
+    }
+
+    Ok(contents)
+}
+
+/// Rewrites every stored file and rehashes the index, capture waits for it on the history lock.
+/// `switch` changes the in-memory state once the files are swapped, hashes are keyed with the new state
+fn rewrite_all_items(
+    key: &EncryptionKey,
+    encrypt: bool,
+    switch: impl FnOnce(),
+) -> Result<(), String> {
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    let mut items = history.items();
+    let files: Vec<Vec<PathBuf>> = items.iter().map(|item| item.files()).collect();
+    let mut contents = rewrite_files(&files.concat(), key, encrypt)?.into_iter();
+
+    switch();
+
+    for (item, files) in items.iter_mut().zip(&files) {
+        let item_contents: Vec<u8> = contents.by_ref().take(files.len()).flatten().collect();
+
+        item.hash = content_hash(&item_contents);
+        item.encrypted = is_enabled();
+    }
+
+    history.replace_items(items);
+    save_history(&history);
+
+    // old thumbnails are named by old hashes and may be stored unencrypted
+    thumbnails::remove_all();
+
+    Ok(())
+}
+
+/// Saves the config with the rewrite marker, or removes the file once encryption is off
+fn save_config(config: Option<&EncryptionConfig>) {
+    match config {
+        Some(config) => write_json_data(FILENAME_ENCRYPTION, config),
+        None => {
+            let _ = fs::remove_file(config_path());
+        }
+    }
+}
+
+/// Finishes a rewrite interrupted by a crash, or one whose revert failed
+fn finish_rewrite(key: EncryptionKey, rewrite: Rewrite) -> Result<(), String> {
+    match rewrite {
+        Rewrite::Encrypt => {
+            rewrite_all_items(&key, true, || {})?;
+
+            let config = get_config_instance()
+                .lock()
+                .clone()
+                .map(|config| EncryptionConfig {
+                    rewriting: None,
+                    ..config
+                });
+            *get_config_instance().lock() = config.clone();
+            save_config(config.as_ref());
+        }
+        Rewrite::Decrypt => {
+            rewrite_all_items(&key, false, || {
+                *get_config_instance().lock() = None;
+                *get_key_instance().lock() = None;
+            })?;
+            save_config(None);
+        }
+    }
+
+    emit_encryption_changed();
+
+    Ok(())
+}
+
+fn emit_encryption_changed() {
+    get_tauri_handle()
+        .emit_all(
+            "clipboard",
+            Payload {
+                message: "encryption".to_string(),
+            },
+        )
+        .unwrap();
+}
+
+#[tauri::command]
+pub fn encryption_status() -> Result<String, String> {
+    let status = EncryptionStatus {
+        enabled: is_enabled(),
+        unlocked: is_unlocked(),
+        key_source: get_config_instance().lock().as_ref().map(|c| c.key_source),
+    };
+
+    serde_json::to_string(&status).map_err(|e| e.to_string())
+}
+
+/// Encrypts the whole history. Without a passphrase a random key is kept in the OS keyring
+#[tauri::command]
+pub async fn enable_encryption(passphrase: Option<String>) -> Result<(), String> {
+    if is_enabled() {
+        return Err("Encryption is already enabled".to_string());
+    }
+
+    let salt: [u8; 32] = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+    let (key_source, key) = match passphrase {
+        Some(passphrase) if !passphrase.is_empty() => {
+            (KeySource::Passphrase, derive_key(&passphrase, &salt)?)
+        }
+        _ => (KeySource::Keyring, keyring_key(true)?),
+    };
+
+    let config = new_config(key_source, &salt, &key)?;
+
+    // saved first, files encrypted before a crash stay readable with the key and the rest is
+    // encrypted on the next unlock
+    save_config(Some(&EncryptionConfig {
+        rewriting: Some(Rewrite::Encrypt),
+        ..config.clone()
+    }));
+
+    let rewritten = rewrite_all_items(&key, true, || {
+        *get_key_instance().lock() = Some(key);
+        *get_config_instance().lock() = Some(config.clone());
+    });
+    if let Err(e) = rewritten {
+        // files swapped before the failure are decrypted back, the marker stays if that fails too
+        if rewrite_all_items(&key, false, || {}).is_ok() {
+            save_config(None);
+        }
+        return Err(e);
+    }
+
+    save_config(Some(&config));
+    emit_encryption_changed();
+
+    Ok(())
+}
+
+/// Decrypts the whole history back to plain files, history must be unlocked
+#[tauri::command]
+pub async fn disable_encryption() -> Result<(), String> {
+    let key = current_key()?;
+    let config = get_config_instance()
+        .lock()
+        .clone()
+        .ok_or("Encryption is not enabled")?;
+
+    save_config(Some(&EncryptionConfig {
+        rewriting: Some(Rewrite::Decrypt),
+        ..config.clone()
+    }));
+
+    let rewritten = rewrite_all_items(&key, false, || {
+        *get_config_instance().lock() = None;
+        *get_key_instance().lock() = None;
+    });
+    if let Err(e) = rewritten {
+        // files swapped before the failure are encrypted back, the marker stays if that fails too
+        if rewrite_all_items(&key, true, || {}).is_ok() {
+            save_config(Some(&config));
+        }
+        return Err(e);
+    }
+
+    save_config(None);
+    emit_encryption_changed();
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unlock_history(passphrase: Option<String>) -> Result<(), String> {
+    unlock(passphrase)?;
+    emit_encryption_changed();
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn lock_history() {
+    *get_key_instance().lock() = None;
+    emit_encryption_changed();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesys::{FILENAME_HISTORY, FOLDER_DATA};
+    use crate::helpers::test_data_dir;
+    use crate::history::{History, HistoryItem};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cboard-crypto-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn round_trips_data() {
+        let key: EncryptionKey = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+        let encrypted = encrypt_with(&key, b"secret").unwrap();
+
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(b"secret"));
+        assert_ne!(encrypt_with(&key, b"secret").unwrap(), encrypted);
+        assert_eq!(decrypt_with(&key, &encrypted).unwrap(), b"secret");
+
+        let other: EncryptionKey = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+        assert!(decrypt_with(&other, &encrypted).is_err());
+        assert!(decrypt_with(&key, b"secret").is_err());
+    }
+
+    #[test]
+    fn checks_passphrase() {
+        let salt = [7u8; 32];
+        let key = derive_key("correct horse", &salt).unwrap();
+        let config = new_config(KeySource::Passphrase, &salt, &key).unwrap();
+
+        assert_eq!(
+            unlock_key(&config, Some("correct horse".to_string())),
+            Ok(key)
+        );
+        assert_eq!(
+            unlock_key(&config, Some("wrong horse".to_string())),
+            Err("Wrong passphrase".to_string())
+        );
+        assert!(unlock_key(&config, None).is_err());
+    }
+
+    #[test]
+    fn rewrites_files_both_ways() {
+        let dir = temp_dir("rewrite");
+        let files = [dir.join("a.txt"), dir.join("b.png")];
+        fs::write(&files[0], b"text").unwrap();
+        fs::write(&files[1], b"image").unwrap();
+
+        let key: EncryptionKey = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+        let contents = rewrite_files(&files, &key, true).unwrap();
+        assert_eq!(contents, [b"text".to_vec(), b"image".to_vec()]);
+        assert!(files.iter().all(|f| is_encrypted(&fs::read(f).unwrap())));
+
+        rewrite_files(&files, &key, false).unwrap();
+        assert_eq!(fs::read(&files[0]).unwrap(), b"text");
+        assert_eq!(fs::read(&files[1]).unwrap(), b"image");
+        assert!(!rewrite_path(&files[0]).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn finishes_an_interrupted_rewrite() {
+        let dir = temp_dir("resume");
+        let files = [dir.join("a.txt"), dir.join("b.png")];
+        let key: EncryptionKey = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+        // the first file was encrypted before the crash
+        fs::write(&files[0], encrypt_with(&key, b"text").unwrap()).unwrap();
+        fs::write(&files[1], b"image").unwrap();
+
+        let contents = rewrite_files(&files, &key, true).unwrap();
+        assert_eq!(contents, [b"text".to_vec(), b"image".to_vec()]);
+        assert_eq!(
+            decrypt_with(&key, &fs::read(&files[0]).unwrap()).unwrap(),
+            b"text"
+        );
+
+        // same when decrypting
+        fs::write(&files[1], b"image").unwrap();
+        rewrite_files(&files, &key, false).unwrap();
+        assert_eq!(fs::read(&files[0]).unwrap(), b"text");
+        assert_eq!(fs::read(&files[1]).unwrap(), b"image");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn leaves_files_untouched_on_failure() {
+        let dir = temp_dir("partial");
+        let files = [
+            dir.join("a.txt"),
+            dir.join("missing.txt"),
+            dir.join("c.txt"),
+        ];
+        fs::write(&files[0], b"first").unwrap();
+        fs::write(&files[2], b"last").unwrap();
+
+        let key: EncryptionKey = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+        assert!(rewrite_files(&files, &key, true).is_err());
+
+        assert_eq!(fs::read(&files[0]).unwrap(), b"first");
+        assert_eq!(fs::read(&files[2]).unwrap(), b"last");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn encrypts_the_index_and_loads_it_on_unlock() {
+        let _dir = test_data_dir::new("crypto-index");
+
+        let salt = [7u8; 32];
+        let key = derive_key("correct horse", &salt).unwrap();
+        *get_config_instance().lock() =
+            Some(new_config(KeySource::Passphrase, &salt, &key).unwrap());
+        *get_key_instance().lock() = Some(key);
+
+        let mut item = HistoryItem::new("1.txt".to_string(), "work".to_string(), b"", vec![]);
+        item.title = Some("bank login".to_string());
+        fs::create_dir_all(history::folder_path("work")).unwrap();
+        write_file(&item.path(), b"").unwrap();
+        let history = get_history_instance();
+        history.lock().add(item);
+        save_history(&history.lock());
+
+        let index = fs::read(app_data_dir().join(FOLDER_DATA).join(FILENAME_HISTORY)).unwrap();
+        assert!(is_encrypted(&index));
+
+        // started again, locked
+        *get_key_instance().lock() = None;
+        *history.lock() = History::default();
+        history::load_history();
+        assert!(!history::is_loaded());
+
+        unlock(Some("correct horse".to_string())).unwrap();
+        assert_eq!(
+            history
+                .lock()
+                .find("work", "1.txt")
+                .unwrap()
+                .title
+                .as_deref(),
+            Some("bank login")
+        );
+
+        *get_config_instance().lock() = None;
+        *get_key_instance().lock() = None;
+    }
+}
@@ -1,5 +1,14 @@
+use crate::clipboard::my_clipboard::files::{self, FileEntry};
 use crate::clipboard::FileTypes;
-use crate::helpers::get_tauri_handle;
+use crate::content_kind::{self, ContentKind};
+use crate::crypto;
+use crate::folders::{self, FolderSettings};
+use crate::helpers::app_data_dir;
+use crate::history::{content_hash, formats_for_extension, get_history_instance, save_history};
+use crate::processes::MyProcess;
+use crate::retention;
+use crate::templates::Template;
+use crate::thumbnails;
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::fs::File;
@@ -24,6 +33,9 @@ pub const FILENAME_AUTO_REPLACEMENT: &str = "autoreplace.json";
 pub const FILENAME_APPS_BLACKLIST: &str = "blacklist.json";
 pub const FILENAME_SETTINGS: &str = "settings.json";
 pub const FILENAME_KEYBOARD_LAYOUTS: &str = "keyboard_layouts.json";
+pub const FILENAME_HISTORY: &str = "history.json";
+pub const FILENAME_ENCRYPTION: &str = "encryption.json";
+pub const FILENAME_FOLDERS: &str = "folders.json";
 
 pub const FILE_MAX_LENGTH: u8 = 255;
 
@@ -33,29 +45,22 @@ pub struct Payload {
 }
 
 #[allow(dead_code)]
-pub fn remove_extra_files(folder: String, max_files_count: u16, app: &tauri::AppHandle) {
-    let path = app
-        .path_resolver()
-        .app_local_data_dir()
-        .expect("Failed to resolve app local dir")
-        .as_path()
-        .join(FOLDER_DATA)
-        .join(folder);
-
-    let files_count = fs::read_dir(&path).unwrap().count() as u16;
-    let files = fs::read_dir(&path).unwrap();
-    if files_count > max_files_count {
-        let mut left_to_remove = files_count - max_files_count;
+pub fn remove_extra_files(folder: String, max_files_count: u16) {
+    let history = get_history_instance();
+    let mut history = history.lock();
 
-        for file in files {
-            if left_to_remove < 1 {
-                break;
-            }
-            left_to_remove -= 1;
+    let removed = history.evict(&folder, max_files_count as usize);
+    if removed.is_empty() {
+        return;
+    }
 
-            fs::remove_file(file.unwrap().path()).unwrap();
+    for file in removed.iter().flat_map(|item| item.files()) {
+        if let Err(e) = fs::remove_file(&file) {
+            eprintln!("Error removing file {:?}: {}", file, e);
         }
     }
+
+    save_history(&history);
 }
 
 #[allow(dead_code)]
@@ -106,6 +111,13 @@ pub async fn delete_all_by_folder(folder: String, app: tauri::AppHandle) {
         let _ = task.await;
     }
 
+    {
+        let history = get_history_instance();
+        let mut history = history.lock();
+        history.remove_folder(&folder);
+        save_history(&history);
+    }
+
     app.emit_all(
         "clipboard",
         Payload {
@@ -125,11 +137,21 @@ pub fn remove_clipboard_item(filename: String, folder: String, app: tauri::AppHa
         .expect("Failed to resolve app local dir")
         .as_path()
         .join(FOLDER_DATA)
-        .join(folder)
-        .join(filename);
+        .join(&folder)
+        .join(&filename);
 
     if fs::remove_file(&file).is_ok() {
         println!("removed file {:?}", file);
+
+        let history = get_history_instance();
+        let mut history = history.lock();
+        if let Some(item) = history.remove(&folder, &filename) {
+            for sidecar in item.files().iter().skip(1) {
+                let _ = fs::remove_file(sidecar);
+            }
+        }
+        save_history(&history);
+
         app.emit_all(
             "clipboard",
             Payload {
@@ -142,6 +164,92 @@ pub fn remove_clipboard_item(filename: String, folder: String, app: tauri::AppHa
     }
 }
 
+/// Replaces contents of a text item and/or its title, the item keeps its name, folder, position and stats.
+/// `title`: None keeps the current one, empty removes it
+#[tauri::command]
+pub fn update_clipboard_item(
+    folder: String,
+    filename: String,
+    contents: Option<String>,
+    title: Option<String>,
+    template: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    let item = {
+        let history = get_history_instance();
+        // capture waits until both the file and the index are updated
+        let mut history = history.lock();
+
+        let item = history
+            .find_mut(&folder, &filename)
+            .ok_or_else(|| format!("Item {} doesn't exist in {}", filename, folder))?;
+
+        if let Some(contents) = contents {
+            if item.extension() != FileTypes::TXT {
+                return Err("Only text items can be edited".to_string());
+            }
+
+            if !retention::fits_item_cap(contents.len() as u64) {
+                return Err("Contents are above the item size cap".to_string());
+            }
+
+            let kind = content_kind::classify(&contents);
+            let bytes = contents.into_bytes();
+
+            // readers never see a half-written file
+            let temp = item.sidecar_path("tmp");
+            crypto::write_file(&temp, &bytes)?;
+            if let Err(e) = fs::rename(&temp, item.path()) {
+                let _ = fs::remove_file(&temp);
+                return Err(e.to_string());
+            }
+
+            // html and rtf versions don't match the edited text anymore
+            for sidecar in item.files().iter().skip(1) {
+                let _ = fs::remove_file(sidecar);
+            }
+
+            item.kind = Some(kind);
+            item.hash = content_hash(&bytes);
+            item.size = bytes.len() as u64;
+            item.formats = formats_for_extension(FileTypes::TXT);
+            item.encrypted = crypto::is_enabled();
+        }
+
+        if let Some(title) = title {
+            let title = title.trim();
+            item.title = (!title.is_empty()).then(|| title.to_string());
+        }
+
+        if let Some(template) = template {
+            // malformed placeholders are reported now rather than on every paste
+            if template {
+                if item.extension() != FileTypes::TXT {
+                    return Err("Only text items can be templates".to_string());
+                }
+                Template::parse(&crypto::read_to_string(&item.path())?)?;
+            }
+
+            item.template = template;
+        }
+
+        let item = item.clone();
+        save_history(&history);
+
+        item
+    };
+
+    app.emit_all(
+        "clipboard",
+        Payload {
+            message: "update_clipboard_item".to_string(),
+        },
+    )
+    .unwrap();
+
+    serde_json::to_string(&item).map_err(|e| e.to_string())
+}
+
 #[allow(dead_code)]
 #[tauri::command]
 pub async fn move_clipboard_item(
@@ -156,11 +264,34 @@ pub async fn move_clipboard_item(
         .expect("Failed to resolve app local dir")
         .as_path()
         .join(FOLDER_DATA)
-        .join(folder)
+        .join(&folder)
         .join(&filename);
 
+    let from_folder = PathBuf::from(&from)
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+
     fs::rename(from, &to).unwrap();
     println!("moved file {} to {:?}", &filename, to);
+
+    {
+        let history = get_history_instance();
+        let mut history = history.lock();
+        if let Some(item) = history.find_mut(&from_folder, &filename) {
+            let sidecars = item.files().into_iter().skip(1).collect::<Vec<_>>();
+            item.folder = folder.clone();
+
+            for (from, to) in sidecars.iter().zip(item.files().iter().skip(1)) {
+                if let Err(e) = fs::rename(from, to) {
+                    eprintln!("Error moving file {:?}: {}", from, e);
+                }
+            }
+        }
+        save_history(&history);
+    }
+
     app.emit_all(
         "clipboard",
         Payload {
@@ -170,15 +301,51 @@ pub async fn move_clipboard_item(
     .unwrap();
 }
 
+#[tauri::command]
+pub fn pin_clipboard_item(
+    filename: String,
+    folder: String,
+    pinned: bool,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    {
+        let history = get_history_instance();
+        let mut history = history.lock();
+
+        let item = history
+            .find_mut(&folder, &filename)
+            .ok_or(format!("Item not found: {}/{}", folder, filename))?;
+        item.pinned = pinned;
+
+        save_history(&history);
+    }
+
+    app.emit_all(
+        "clipboard",
+        Payload {
+            message: "pin_clipboard_item".to_string(),
+        },
+    )
+    .unwrap();
+
+    Ok(())
+}
+
+/// Url of the full size image, the listing only has thumbnails
+#[tauri::command]
+pub fn read_full_image(filename: String, folder: String) -> Result<String, String> {
+    let item = get_history_instance()
+        .lock()
+        .find(&folder, &filename)
+        .cloned()
+        .ok_or(format!("Item not found: {}/{}", folder, filename))?;
+
+    thumbnails::image_url(item.path(), item.encrypted).ok_or("Couldn't read image".to_string())
+}
+
 #[allow(dead_code)]
 pub fn create_folders<T: AsRef<str>>(folders: &[T]) -> std::io::Result<()> {
-    let app = get_tauri_handle().clone();
-    let to = app
-        .path_resolver()
-        .app_local_data_dir()
-        .expect("Failed to resolve app local dir")
-        .as_path()
-        .join(FOLDER_DATA);
+    let to = app_data_dir().join(FOLDER_DATA);
 
     for folder in folders {
         let full_path = to.clone().join(folder.as_ref());
@@ -188,7 +355,7 @@ pub fn create_folders<T: AsRef<str>>(folders: &[T]) -> std::io::Result<()> {
     Ok(())
 }
 
-trait PathBufTauri {
+pub trait PathBufTauri {
     fn asset_path(self) -> String;
 }
 
@@ -232,24 +399,35 @@ struct StorageFile {
     folder: String,
     size: u64,
     contents: Option<String>,
+    files: Option<Vec<FileEntry>>,
+    created_at: u64,
+    last_used_at: u64,
+    hash: String,
+    formats: Vec<String>,
+    source: Option<MyProcess>,
+    paste_count: u32,
+    pinned: bool,
+    encrypted: bool,
+    expires_at: Option<u64>,
+    width: Option<u32>,
+    height: Option<u32>,
+    title: Option<String>,
+    kind: Option<ContentKind>,
+    template: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct StorageFolder {
     path: String,
     name: String,
+    /// None for built-in folders
+    settings: Option<FolderSettings>,
     children: Vec<StorageFile>,
 }
 
 #[tauri::command]
 pub async fn read_clipboard_data() -> Result<String, String> {
-    let app = get_tauri_handle().clone();
-    let dir = app
-        .path_resolver()
-        .app_local_data_dir()
-        .expect("Failed to resolve app local dir")
-        .as_path()
-        .join(FOLDER_DATA);
+    let dir = app_data_dir().join(FOLDER_DATA);
 
     if !dir.is_dir() {
         return Err("Specified path is not a dir".to_string());
@@ -257,53 +435,95 @@ pub async fn read_clipboard_data() -> Result<String, String> {
 
     let mut data: Vec<StorageFolder> = Vec::new();
 
-    let entries = fs::read_dir(dir)
+    let mut entries = fs::read_dir(dir)
         .unwrap()
         .filter(|e| e.as_ref().unwrap().path().is_dir())
         .map(|res| res.map(|e| e.path()))
         .collect::<Result<Vec<_>, io::Error>>()
         .unwrap();
 
+    // tabs rely on built-in folders going first, then user folders in the order of creation
+    let names = folders::folder_names();
+    entries.sort_by_key(|e| {
+        let name = e.file_name().unwrap().to_string_lossy().to_string();
+        (
+            names.iter().position(|n| *n == name).unwrap_or(names.len()),
+            name,
+        )
+    });
+
     for subdir in entries {
-        let files = fs::read_dir(&subdir)
-            .unwrap()
-            .collect::<Result<Vec<_>, io::Error>>()
-            .unwrap();
+        let folder = subdir.file_name().unwrap().to_string_lossy().to_string();
+        let settings = folders::folder_settings(&folder);
+        let mut items = get_history_instance().lock().folder_items(&folder);
+        if let Some(settings) = &settings {
+            folders::sort_items(&mut items, settings.sort);
+        }
 
         let mut children = Vec::new();
 
-        for file in files {
-            let extension = file
-                .path()
-                .extension()
-                .unwrap()
-                .to_string_lossy()
-                .to_string();
+        for item in items {
+            let path = item.path();
+            let extension = item.extension();
+            let kind = content_kind::of_item(&item);
+            let file_list = match extension.as_str() {
+                FileTypes::FILES => crypto::read_file(&path).ok().map(|bytes| {
+                    files::decode(&bytes)
+                        .iter()
+                        .map(|p| FileEntry::from_path(p))
+                        .collect::<Vec<_>>()
+                }),
+                _ => None,
+            };
+
             let contents = match extension.as_str() {
-                FileTypes::TXT => Some(
-                    read_file_by_char_len(&file.path(), FILE_MAX_LENGTH).unwrap_or("".to_string()),
-                ),
-                FileTypes::PNG => Some(&file.path().asset_path()).cloned(),
+                // encrypted files can't be read partially, and webview can't load them by url
+                FileTypes::TXT if item.encrypted => crypto::read_to_string(&path)
+                    .ok()
+                    .map(|text| text.chars().take(FILE_MAX_LENGTH as usize).collect()),
+                // full size images are loaded on demand with read_full_image
+                FileTypes::PNG => thumbnails::url(&item),
+                FileTypes::TXT => {
+                    Some(read_file_by_char_len(&path, FILE_MAX_LENGTH).unwrap_or("".to_string()))
+                }
+                FileTypes::FILES => file_list.as_ref().map(|list| {
+                    list.iter()
+                        .map(|f| f.name.clone())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }),
                 _ => None,
             };
 
-            let file_data = StorageFile {
+            children.push(StorageFile {
                 contents,
-                folder: subdir.file_name().unwrap().to_string_lossy().to_string(),
-                name: file.file_name().to_string_lossy().to_string(),
-                path: file.path().to_string_lossy().to_string(),
+                files: file_list,
+                folder: item.folder,
+                name: item.name,
+                path: path.to_string_lossy().to_string(),
                 extension,
-                size: file.metadata().unwrap().len(),
-            };
-
-            children.push(file_data);
+                size: item.size,
+                created_at: item.created_at,
+                last_used_at: item.last_used_at,
+                hash: item.hash,
+                formats: item.formats,
+                source: item.source,
+                paste_count: item.paste_count,
+                pinned: item.pinned,
+                encrypted: item.encrypted,
+                expires_at: item.expires_at,
+                width: item.width,
+                height: item.height,
+                title: item.title,
+                kind,
+                template: item.template,
+            });
         }
 
-        children.sort_by(|a, b| b.name.cmp(&a.name));
-
         data.push(StorageFolder {
             path: subdir.as_path().to_string_lossy().to_string(),
-            name: subdir.file_name().unwrap().to_string_lossy().to_string(),
+            name: folder,
+            settings,
             children,
         });
     }
@@ -314,14 +534,7 @@ pub async fn read_clipboard_data() -> Result<String, String> {
 pub fn read_json_data<T: DeserializeOwned>(
     filename: &str,
 ) -> Result<T, Box<dyn std::error::Error>> {
-    let app = get_tauri_handle().clone();
-    let from = app
-        .path_resolver()
-        .app_local_data_dir()
-        .expect("Failed to resolve app local dir")
-        .as_path()
-        .join("data")
-        .join(filename);
+    let from = app_data_dir().join("data").join(filename);
 
     let file = File::open(from)?;
     let reader = BufReader::new(file);
@@ -336,14 +549,7 @@ pub fn read_json_data<T: DeserializeOwned>(
 }
 
 pub fn write_json_data<T: Serialize>(filename: &str, data: &T) {
-    let app = get_tauri_handle().clone();
-    let file = app
-        .path_resolver()
-        .app_local_data_dir()
-        .expect("Failed to resolve app local dir")
-        .as_path()
-        .join("data")
-        .join(filename);
+    let file = app_data_dir().join("data").join(filename);
 
     let json_data = match serde_json::to_string_pretty(data) {
         Ok(data) => data,
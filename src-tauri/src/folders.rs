@@ -0,0 +1,397 @@
+use crate::filesys::{
+    read_json_data, write_json_data, Payload, FILENAME_FOLDERS, FOLDER_CLIPBOARD, FOLDER_FAVOURITES,
+};
+use crate::helpers::{get_tauri_handle, get_timestamp_millis};
+use crate::history::{self, get_history_instance, save_history, HistoryItem};
+use crate::retention;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::sync::{Arc, OnceLock};
+use tauri::Manager;
+
+pub const FOLDER_NAME_MAX_LENGTH: usize = 64;
+
+/// Built-in folders can't be created, renamed or deleted
+const BUILTIN_FOLDERS: [&str; 2] = [FOLDER_CLIPBOARD, FOLDER_FAVOURITES];
+
+/// Names Windows doesn't allow for files and dirs, with or without extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// most recently used first, like the clipboard folder
+    #[default]
+    LastUsed,
+    /// most recently captured first
+    CreatedAt,
+    /// most pasted first
+    PasteCount,
+}
+
+/// User-defined folder, listed after the built-in ones in the order of creation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderSettings {
+    pub name: String,
+    /// ms since unix epoch
+    pub created_at: u64,
+    /// oldest unpinned items above it are removed, 0 - unlimited
+    #[serde(default)]
+    pub max_count: u16,
+    #[serde(default)]
+    pub sort: SortOrder,
+}
+
+pub static FOLDERS: OnceLock<Arc<Mutex<Vec<FolderSettings>>>> = OnceLock::new();
+
+pub fn get_folders_instance() -> Arc<Mutex<Vec<FolderSettings>>> {
+    FOLDERS.get_or_init(|| Arc::new(Mutex::new(vec![]))).clone()
+}
+
+pub fn folder_settings(name: &str) -> Option<FolderSettings> {
+    get_folders_instance()
+        .lock()
+        .iter()
+        .find(|f| f.name == name)
+        .cloned()
+}
+
+/// Built-in folders first, then user folders
+pub fn folder_names() -> Vec<String> {
+    BUILTIN_FOLDERS
+        .iter()
+        .map(|f| f.to_string())
+        .chain(get_folders_instance().lock().iter().map(|f| f.name.clone()))
+        .collect()
+}
+
+pub fn sort_items(items: &mut [HistoryItem], sort: SortOrder) {
+    match sort {
+        // history keeps items of a folder in this order already
+        SortOrder::LastUsed => {}
+        SortOrder::CreatedAt => items.sort_by_key(|i| Reverse(i.created_at)),
+        SortOrder::PasteCount => items.sort_by_key(|i| Reverse(i.paste_count)),
+    }
+
+    // stable sort keeps the chosen order inside both groups
+    items.sort_by_key(|i| !i.pinned);
+}
+
+#[tauri::command]
+pub fn update_folders_data() -> Result<(), String> {
+    match read_json_data::<Vec<FolderSettings>>(FILENAME_FOLDERS) {
+        Ok(data) => {
+            for folder in data.iter() {
+                let _ = fs::create_dir_all(history::folder_path(&folder.name));
+            }
+
+            *get_folders_instance().lock() = data;
+        }
+        Err(_) => save(&get_folders_instance().lock()),
+    }
+
+    Ok(())
+}
+
+fn save(folders: &Vec<FolderSettings>) {
+    write_json_data(FILENAME_FOLDERS, folders);
+}
+
+fn emit_contents_changed() {
+    get_tauri_handle()
+        .emit_all(
+            "clipboard",
+            Payload {
+                message: String::from("contents"),
+            },
+        )
+        .unwrap();
+}
+
+/// Trimmed name if it can be used as a new folder dir on every platform,
+/// `renamed` is the current name of a folder being renamed, so its case can be changed
+pub fn validate_name(
+    folders: &[FolderSettings],
+    name: &str,
+    renamed: Option<&str>,
+) -> Result<String, String> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err("Folder name is empty".to_string());
+    }
+
+    if name.chars().count() > FOLDER_NAME_MAX_LENGTH {
+        return Err(format!(
+            "Folder name is longer than {} characters",
+            FOLDER_NAME_MAX_LENGTH
+        ));
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| c.is_control() || "<>:\"/\\|?*".contains(*c))
+    {
+        return Err(format!("Folder name can't contain {:?}", c));
+    }
+
+    if name.starts_with('.') || name.ends_with('.') {
+        return Err("Folder name can't start or end with a dot".to_string());
+    }
+
+    let stem = name.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        return Err(format!("{} is a reserved name", name));
+    }
+
+    // dirs are case insensitive on Windows and macOS
+    let same = |a: &str, b: &str| a.to_lowercase() == b.to_lowercase();
+    if renamed.is_some_and(|renamed| same(renamed, name)) {
+        return Ok(name.to_string());
+    }
+
+    let taken = BUILTIN_FOLDERS
+        .iter()
+        .copied()
+        .chain(folders.iter().map(|f| f.name.as_str()))
+        .any(|f| same(f, name));
+    if taken || history::folder_path(name).exists() {
+        return Err(format!("Folder {} already exists", name));
+    }
+
+    Ok(name.to_string())
+}
+
+fn user_folder_index(folders: &[FolderSettings], name: &str) -> Result<usize, String> {
+    if BUILTIN_FOLDERS.contains(&name) {
+        return Err(format!("{} is a built-in folder", name));
+    }
+
+    folders
+        .iter()
+        .position(|f| f.name == name)
+        .ok_or_else(|| format!("Folder {} doesn't exist", name))
+}
+
+#[tauri::command]
+pub fn get_folders() -> Result<String, String> {
+    serde_json::to_string(&*get_folders_instance().lock()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_folder(name: String) -> Result<String, String> {
+    let folders = get_folders_instance();
+    let mut folders = folders.lock();
+
+    let name = validate_name(&folders, &name, None)?;
+    fs::create_dir_all(history::folder_path(&name)).map_err(|e| e.to_string())?;
+
+    let folder = FolderSettings {
+        name,
+        created_at: get_timestamp_millis(),
+        max_count: 0,
+        sort: SortOrder::default(),
+    };
+    folders.push(folder.clone());
+    save(&folders);
+    drop(folders);
+
+    emit_contents_changed();
+
+    serde_json::to_string(&folder).map_err(|e| e.to_string())
+}
+
+/// Renames the dir and moves items of the folder to the new name
+#[tauri::command]
+pub fn rename_folder(name: String, new_name: String) -> Result<String, String> {
+    let folders = get_folders_instance();
+    let mut folders = folders.lock();
+
+    let index = user_folder_index(&folders, &name)?;
+    let new_name = validate_name(&folders, &new_name, Some(&name))?;
+
+    fs::rename(history::folder_path(&name), history::folder_path(&new_name))
+        .map_err(|e| e.to_string())?;
+
+    {
+        let history = get_history_instance();
+        let mut history = history.lock();
+        history.rename_folder(&name, &new_name);
+        save_history(&history);
+    }
+
+    folders[index].name = new_name;
+    let folder = folders[index].clone();
+    save(&folders);
+    drop(folders);
+
+    emit_contents_changed();
+
+    serde_json::to_string(&folder).map_err(|e| e.to_string())
+}
+
+/// Removes the folder with all its items, pinned ones too
+#[tauri::command]
+pub fn delete_folder(name: String) -> Result<(), String> {
+    let folders = get_folders_instance();
+    let mut folders = folders.lock();
+
+    let index = user_folder_index(&folders, &name)?;
+
+    {
+        let history = get_history_instance();
+        let mut history = history.lock();
+        history.remove_folder(&name);
+        save_history(&history);
+    }
+
+    if let Err(e) = fs::remove_dir_all(history::folder_path(&name)) {
+        eprintln!("Error removing folder {}: {}", name, e);
+    }
+
+    folders.remove(index);
+    save(&folders);
+    drop(folders);
+
+    emit_contents_changed();
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn update_folder_settings(
+    name: String,
+    max_count: u16,
+    sort: SortOrder,
+) -> Result<String, String> {
+    let folder = {
+        let folders = get_folders_instance();
+        let mut folders = folders.lock();
+
+        let index = user_folder_index(&folders, &name)?;
+        folders[index].max_count = max_count;
+        folders[index].sort = sort;
+        save(&folders);
+
+        folders[index].clone()
+    };
+
+    retention::enforce_and_notify();
+    emit_contents_changed();
+
+    serde_json::to_string(&folder).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::test_data_dir;
+
+    fn folder(name: &str) -> FolderSettings {
+        FolderSettings {
+            name: name.to_string(),
+            created_at: 0,
+            max_count: 0,
+            sort: SortOrder::default(),
+        }
+    }
+
+    fn check(cases: &[(&str, Result<&str, &str>)]) {
+        let _dir = test_data_dir::new("folders");
+        let folders = [folder("Work")];
+
+        for (name, expected) in cases {
+            let expected = expected.map(str::to_string).map_err(str::to_string);
+            assert_eq!(validate_name(&folders, name, None), expected, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_reserved_names() {
+        check(&[
+            ("CON", Err("CON is a reserved name")),
+            ("nul", Err("nul is a reserved name")),
+            ("Com1.txt", Err("Com1.txt is a reserved name")),
+            ("lpt9.tar.gz", Err("lpt9.tar.gz is a reserved name")),
+            ("CONSOLE", Ok("CONSOLE")),
+            ("COM10", Ok("COM10")),
+            ("my.con", Ok("my.con")),
+        ]);
+    }
+
+    #[test]
+    fn rejects_invalid_chars() {
+        check(&[
+            ("a<b", Err("Folder name can't contain '<'")),
+            ("a>b", Err("Folder name can't contain '>'")),
+            ("a:b", Err("Folder name can't contain ':'")),
+            ("a\"b", Err("Folder name can't contain '\"'")),
+            ("a/b", Err("Folder name can't contain '/'")),
+            ("a\\b", Err("Folder name can't contain '\\\\'")),
+            ("a|b", Err("Folder name can't contain '|'")),
+            ("a?b", Err("Folder name can't contain '?'")),
+            ("a*b", Err("Folder name can't contain '*'")),
+            ("a\tb", Err("Folder name can't contain '\\t'")),
+            ("", Err("Folder name is empty")),
+            ("   ", Err("Folder name is empty")),
+            ("  Notes  ", Ok("Notes")),
+            ("Ünïcode папка", Ok("Ünïcode папка")),
+        ]);
+    }
+
+    #[test]
+    fn rejects_leading_and_trailing_dots() {
+        check(&[
+            ("notes.", Err("Folder name can't start or end with a dot")),
+            ("notes...", Err("Folder name can't start or end with a dot")),
+            (".notes", Err("Folder name can't start or end with a dot")),
+            ("..", Err("Folder name can't start or end with a dot")),
+            ("notes. ", Err("Folder name can't start or end with a dot")),
+            ("v1.2 notes", Ok("v1.2 notes")),
+        ]);
+    }
+
+    #[test]
+    fn rejects_duplicates_ignoring_case() {
+        check(&[
+            ("Work", Err("Folder Work already exists")),
+            ("WORK", Err("Folder WORK already exists")),
+            ("work", Err("Folder work already exists")),
+            ("Clipboard", Err("Folder Clipboard already exists")),
+            ("FAVORITES", Err("Folder FAVORITES already exists")),
+            ("Workspace", Ok("Workspace")),
+        ]);
+    }
+
+    #[test]
+    fn allows_renaming_to_a_different_case() {
+        let _dir = test_data_dir::new("folders");
+        let folders = [folder("Work"), folder("Home")];
+
+        assert_eq!(
+            validate_name(&folders, "WORK", Some("Work")),
+            Ok("WORK".to_string())
+        );
+        assert_eq!(
+            validate_name(&folders, "home", Some("Work")),
+            Err("Folder home already exists".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_names_longer_than_the_limit() {
+        let longest = "a".repeat(FOLDER_NAME_MAX_LENGTH);
+        let too_long = "ы".repeat(FOLDER_NAME_MAX_LENGTH + 1);
+        let error = format!(
+            "Folder name is longer than {} characters",
+            FOLDER_NAME_MAX_LENGTH
+        );
+
+        check(&[(&longest, Ok(&longest)), (&too_long, Err(&error))]);
+    }
+}
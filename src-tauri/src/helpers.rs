@@ -1,10 +1,10 @@
 use serde::Serialize;
+use std::path::PathBuf;
 use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::AppHandle;
-use std::iter::once;
-use std::ffi::OsStr;
-use std::os::windows::ffi::OsStrExt;
+#[cfg(target_os = "windows")]
+use std::{ffi::OsStr, iter::once, os::windows::ffi::OsStrExt};
 
 #[derive(Clone, Serialize)]
 pub struct EmptyPayload;
@@ -15,13 +15,29 @@ pub fn get_tauri_handle() -> &'static AppHandle {
     APP_HANDLE.get().expect("AppHandle is not set")
 }
 
+/// App local data dir, history and config files are kept in its `data` subdir
+pub fn app_data_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = test_data_dir::get() {
+        return dir;
+    }
+
+    get_tauri_handle()
+        .path_resolver()
+        .app_local_data_dir()
+        .expect("Failed to resolve app local dir")
+}
+
 #[allow(dead_code)]
 pub fn get_timestamp() -> String {
+    get_timestamp_millis().to_string()
+}
+
+pub fn get_timestamp_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
-        .as_millis()
-        .to_string()
+        .as_millis() as u64
 }
 
 pub fn print_type_of<T>(_: &T) {
@@ -32,6 +48,111 @@ pub fn is_alphanumeric_or_space(s: &str) -> bool {
     s.chars().all(|c| c.is_alphanumeric() || c.is_whitespace())
 }
 
+#[cfg(target_os = "windows")]
 pub fn to_wide_string(s: &str) -> Vec<u16> {
     OsStr::new(s).encode_wide().chain(once(0)).collect()
-}
\ No newline at end of file
+}
+
+/// Temp app data dir for tests of code reading and writing history files
+#[cfg(test)]
+pub mod test_data_dir {
+    use crate::folders::get_folders_instance;
+    use crate::history::{get_history_instance, History};
+    use crate::settings::get_settings_instance;
+    use parking_lot::{Mutex, MutexGuard};
+    use std::fs;
+    use std::path::PathBuf;
+
+    static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+    /// History is global, tests using it run one at a time
+    static IN_USE: Mutex<()> = Mutex::new(());
+
+    pub fn get() -> Option<PathBuf> {
+        DIR.lock().clone()
+    }
+
+    /// Empty data dir, history, user folders and retention limits until dropped
+    pub struct TestDataDir {
+        pub path: PathBuf,
+        _in_use: MutexGuard<'static, ()>,
+    }
+
+    pub fn new(name: &str) -> TestDataDir {
+        let in_use = IN_USE.lock();
+
+        let path = std::env::temp_dir().join(format!("cboard-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(path.join(crate::filesys::FOLDER_DATA)).unwrap();
+
+        *DIR.lock() = Some(path.clone());
+        *get_history_instance().lock() = History::default();
+        get_folders_instance().lock().clear();
+
+        let settings = get_settings_instance();
+        let mut settings = settings.lock();
+        settings.folder_max_age_days.clear();
+        settings.history_max_bytes = 0;
+        settings.item_max_bytes = 0;
+        drop(settings);
+
+        TestDataDir {
+            path,
+            _in_use: in_use,
+        }
+    }
+
+    impl Drop for TestDataDir {
+        fn drop(&mut self) {
+            *DIR.lock() = None;
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// History items for tests, stored ones get their files in the test data dir
+#[cfg(test)]
+pub mod test_items {
+    use crate::clipboard::FileTypes;
+    use crate::history::{folder_path, formats_for_extension, get_history_instance, HistoryItem};
+    use std::fs;
+
+    /// Item named after `created_at`, which is also its last use time
+    pub fn item(folder: &str, created_at: u64, extension: &str, contents: &[u8]) -> HistoryItem {
+        let mut item = HistoryItem::new(
+            format!("{}.{}", created_at, extension),
+            folder.to_string(),
+            contents,
+            formats_for_extension(extension),
+        );
+        item.created_at = created_at;
+        item.last_used_at = created_at;
+
+        item
+    }
+
+    pub fn text_item(folder: &str, created_at: u64, text: &str) -> HistoryItem {
+        item(folder, created_at, FileTypes::TXT, text.as_bytes())
+    }
+
+    /// Writes `files` of the item the way capture does and adds it to history
+    pub fn store(item: HistoryItem, files: &[&[u8]]) -> HistoryItem {
+        fs::create_dir_all(folder_path(&item.folder)).unwrap();
+        for (file, data) in item.files().iter().zip(files) {
+            fs::write(file, data).unwrap();
+        }
+        get_history_instance().lock().add(item.clone());
+
+        item
+    }
+
+    /// Stores an item with a single file
+    pub fn store_file(
+        folder: &str,
+        created_at: u64,
+        extension: &str,
+        contents: &[u8],
+    ) -> HistoryItem {
+        store(item(folder, created_at, extension, contents), &[contents])
+    }
+}
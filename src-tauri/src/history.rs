@@ -0,0 +1,670 @@
+use crate::clipboard::FileTypes;
+use crate::content_kind::ContentKind;
+use crate::crypto;
+use crate::filesys::{write_json_data, FILENAME_HISTORY, FOLDER_DATA};
+use crate::helpers::{app_data_dir, get_timestamp_millis};
+use crate::phash::Fingerprint;
+use crate::processes::MyProcess;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+pub struct MimeTypes;
+
+impl MimeTypes {
+    pub const TEXT_PLAIN: &'static str = "text/plain";
+    pub const TEXT_HTML: &'static str = "text/html";
+    pub const TEXT_RTF: &'static str = "text/rtf";
+    pub const IMAGE_PNG: &'static str = "image/png";
+    pub const URI_LIST: &'static str = "text/uri-list";
+}
+
+/// Metadata of a single stored clipboard item, the file itself lives in `data/<folder>/<name>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryItem {
+    pub name: String,
+    pub folder: String,
+    /// ms since unix epoch
+    pub created_at: u64,
+    /// ms since unix epoch, used for sorting
+    pub last_used_at: u64,
+    /// sha256 of the stored file contents
+    pub hash: String,
+    pub size: u64,
+    pub formats: Vec<String>,
+    pub source: Option<MyProcess>,
+    pub paste_count: u32,
+    /// pinned items are never evicted
+    #[serde(default)]
+    pub pinned: bool,
+    /// files of the item are encrypted, see `crypto`
+    #[serde(default)]
+    pub encrypted: bool,
+    /// ms since unix epoch, sensitive items are deleted after it, see `sensitive`
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// original dimensions of images
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// perceptual hash of images, see `phash`
+    #[serde(default)]
+    pub phash: Option<u64>,
+    /// shown instead of the contents when set by the user
+    #[serde(default)]
+    pub title: Option<String>,
+    /// what text items look like, see `content_kind`
+    #[serde(default)]
+    pub kind: Option<ContentKind>,
+    /// placeholders are expanded on paste, see `templates`
+    #[serde(default)]
+    pub template: bool,
+}
+
+impl HistoryItem {
+    pub fn new(name: String, folder: String, bytes: &[u8], formats: Vec<String>) -> Self {
+        let now = get_timestamp_millis();
+
+        HistoryItem {
+            name,
+            folder,
+            created_at: now,
+            last_used_at: now,
+            hash: content_hash(bytes),
+            size: bytes.len() as u64,
+            formats,
+            source: None,
+            paste_count: 0,
+            pinned: false,
+            encrypted: false,
+            expires_at: None,
+            width: None,
+            height: None,
+            phash: None,
+            title: None,
+            kind: None,
+            template: false,
+        }
+    }
+
+    pub fn extension(&self) -> String {
+        PathBuf::from(&self.name)
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn path(&self) -> PathBuf {
+        folder_path(&self.folder).join(&self.name)
+    }
+
+    /// Path of an alternative format stored next to the main file, e.g. `<name>.html`
+    pub fn sidecar_path(&self, extension: &str) -> PathBuf {
+        self.path().with_extension(extension)
+    }
+
+    pub fn has_format(&self, mime: &str) -> bool {
+        self.formats.iter().any(|f| f == mime)
+    }
+
+    /// Main file and every sidecar file of the item
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.path()];
+        for format in self.formats.iter().skip(1) {
+            if let Some(extension) = extension_for_mime(format) {
+                files.push(self.sidecar_path(extension));
+            }
+        }
+
+        files
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    items: Vec<HistoryItem>,
+}
+
+impl History {
+    pub fn add(&mut self, item: HistoryItem) {
+        self.items.push(item);
+    }
+
+    pub fn items(&self) -> Vec<HistoryItem> {
+        self.items.clone()
+    }
+
+    pub fn replace_items(&mut self, items: Vec<HistoryItem>) {
+        self.items = items;
+    }
+
+    pub fn find(&self, folder: &str, name: &str) -> Option<&HistoryItem> {
+        self.items
+            .iter()
+            .find(|i| i.folder == folder && i.name == name)
+    }
+
+    pub fn find_mut(&mut self, folder: &str, name: &str) -> Option<&mut HistoryItem> {
+        self.items
+            .iter_mut()
+            .find(|i| i.folder == folder && i.name == name)
+    }
+
+    pub fn find_by_hash_mut<'a>(
+        &'a mut self,
+        hash: &'a str,
+    ) -> impl Iterator<Item = &'a mut HistoryItem> + 'a {
+        self.items.iter_mut().filter(move |i| i.hash == hash)
+    }
+
+    pub fn remove(&mut self, folder: &str, name: &str) -> Option<HistoryItem> {
+        let index = self
+            .items
+            .iter()
+            .position(|i| i.folder == folder && i.name == name)?;
+
+        Some(self.items.remove(index))
+    }
+
+    pub fn remove_folder(&mut self, folder: &str) -> Vec<HistoryItem> {
+        let (removed, kept) = self.items.drain(..).partition(|i| i.folder == folder);
+        self.items = kept;
+
+        removed
+    }
+
+    pub fn rename_folder(&mut self, folder: &str, new_name: &str) {
+        for item in self.items.iter_mut().filter(|i| i.folder == folder) {
+            item.folder = new_name.to_string();
+        }
+    }
+
+    /// Items of a folder, pinned first and then most recently used first
+    pub fn folder_items(&self, folder: &str) -> Vec<HistoryItem> {
+        let mut items: Vec<HistoryItem> = self
+            .items
+            .iter()
+            .filter(|i| i.folder == folder)
+            .cloned()
+            .collect();
+
+        items.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then_with(|| b.last_used_at.cmp(&a.last_used_at))
+                .then_with(|| b.name.cmp(&a.name))
+        });
+
+        items
+    }
+
+    /// Removes the oldest (by capture time) unpinned items of a folder above `max_count` and returns them.
+    /// Pinned items don't count towards the limit
+    pub fn evict(&mut self, folder: &str, max_count: usize) -> Vec<HistoryItem> {
+        let mut items: Vec<HistoryItem> = self
+            .folder_items(folder)
+            .into_iter()
+            .filter(|i| !i.pinned)
+            .collect();
+        if items.len() <= max_count {
+            return vec![];
+        }
+
+        items.sort_by_key(|i| i.created_at);
+        let to_remove = items.len() - max_count;
+
+        items
+            .into_iter()
+            .take(to_remove)
+            .filter_map(|i| self.remove(&i.folder, &i.name))
+            .collect()
+    }
+}
+
+pub static HISTORY: OnceLock<Arc<Mutex<History>>> = OnceLock::new();
+
+pub fn get_history_instance() -> Arc<Mutex<History>> {
+    HISTORY
+        .get_or_init(|| Arc::new(Mutex::new(History::default())))
+        .clone()
+}
+
+/// Set once the index is read, an encrypted index can't be read before history is unlocked
+static INDEX_LOADED: AtomicBool = AtomicBool::new(false);
+
+pub fn is_loaded() -> bool {
+    INDEX_LOADED.load(Ordering::Relaxed)
+}
+
+fn index_path() -> PathBuf {
+    app_data_dir().join(FOLDER_DATA).join(FILENAME_HISTORY)
+}
+
+/// The index holds titles and source window titles, so it's encrypted like item files.
+/// Nothing is saved while history is locked, changes made meanwhile are saved on unlock
+pub fn save_history(history: &History) {
+    if !crypto::is_enabled() {
+        write_json_data(FILENAME_HISTORY, history);
+        return;
+    }
+
+    let saved = serde_json::to_vec(history)
+        .map_err(|e| e.to_string())
+        .and_then(|json| crypto::write_file(&index_path(), &json));
+    if let Err(e) = saved {
+        eprintln!("History index isn't saved: {}", e);
+    }
+}
+
+fn read_index() -> Result<History, String> {
+    let data = crypto::read_file(&index_path())?;
+
+    serde_json::from_slice(&data).map_err(|e| e.to_string())
+}
+
+/// Reads the index and reconciles it with files actually present on disk
+pub fn load_history() {
+    let mut history = match read_index() {
+        Ok(history) => history,
+        // rebuilt from files it would lose titles, sources and pins
+        Err(e) if crypto::is_enabled() && !crypto::is_unlocked() => {
+            println!("History index is loaded on unlock: {}", e);
+            INDEX_LOADED.store(false, Ordering::Relaxed);
+            return;
+        }
+        Err(e) => {
+            println!("History index not found, rebuilding: {}", e);
+            History::default()
+        }
+    };
+
+    // drop entries whose files were removed outside of the app
+    history.items.retain(|i| i.path().is_file());
+
+    for folder in data_folders() {
+        let Ok(entries) = fs::read_dir(folder_path(&folder)) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            if history.find(&folder, &name).is_some() {
+                continue;
+            }
+
+            if let Some(item) = item_from_file(&folder, &path) {
+                history.add(item);
+            }
+        }
+    }
+
+    save_history(&history);
+
+    let instance = get_history_instance();
+    *instance.lock() = history;
+    INDEX_LOADED.store(true, Ordering::Relaxed);
+}
+
+/// Builds metadata for a file which isn't in the index yet (e.g. saved by an older version)
+fn item_from_file(folder: &str, path: &Path) -> Option<HistoryItem> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let extension = path.extension()?.to_string_lossy().to_string();
+    let formats = formats_for_extension(&extension);
+    if formats.is_empty() {
+        // sidecar files are indexed together with their main file
+        return None;
+    }
+
+    let bytes = fs::read(path).ok()?;
+    let encrypted = crypto::is_encrypted(&bytes);
+    let bytes = if encrypted {
+        crypto::read_file(path).unwrap_or(bytes)
+    } else {
+        bytes
+    };
+
+    let mut item = HistoryItem::new(name, folder.to_string(), &bytes, formats);
+    item.encrypted = encrypted;
+
+    // old files are named by capture timestamp
+    let created_at = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(item.created_at);
+    item.created_at = created_at;
+    item.last_used_at = created_at;
+
+    Some(item)
+}
+
+/// Formats of a main file, sidecar extensions produce nothing
+pub fn formats_for_extension(extension: &str) -> Vec<String> {
+    match extension {
+        FileTypes::TXT => vec![MimeTypes::TEXT_PLAIN.to_string()],
+        FileTypes::PNG => vec![MimeTypes::IMAGE_PNG.to_string()],
+        FileTypes::FILES => vec![MimeTypes::URI_LIST.to_string()],
+        _ => vec![],
+    }
+}
+
+pub fn mime_for_extension(extension: &str) -> Option<&'static str> {
+    match extension {
+        FileTypes::TXT => Some(MimeTypes::TEXT_PLAIN),
+        FileTypes::HTML => Some(MimeTypes::TEXT_HTML),
+        FileTypes::RTF => Some(MimeTypes::TEXT_RTF),
+        FileTypes::PNG => Some(MimeTypes::IMAGE_PNG),
+        FileTypes::FILES => Some(MimeTypes::URI_LIST),
+        _ => None,
+    }
+}
+
+pub fn extension_for_mime(mime: &str) -> Option<&'static str> {
+    match mime {
+        MimeTypes::TEXT_PLAIN => Some(FileTypes::TXT),
+        MimeTypes::TEXT_HTML => Some(FileTypes::HTML),
+        MimeTypes::TEXT_RTF => Some(FileTypes::RTF),
+        MimeTypes::IMAGE_PNG => Some(FileTypes::PNG),
+        MimeTypes::URI_LIST => Some(FileTypes::FILES),
+        _ => None,
+    }
+}
+
+/// sha256 of contents, keyed while history is encrypted
+pub fn content_hash(bytes: &[u8]) -> String {
+    match crypto::hash_key() {
+        Some(key) => format!("{:x}", Sha256::digest([key.as_slice(), bytes].concat())),
+        None => format!("{:x}", Sha256::digest(bytes)),
+    }
+}
+
+pub fn folder_path(folder: &str) -> PathBuf {
+    app_data_dir().join(FOLDER_DATA).join(folder)
+}
+
+pub fn data_folders() -> Vec<String> {
+    let dir = app_data_dir().join(FOLDER_DATA);
+
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|e| e.path().is_dir())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+pub fn mark_pasted(folder: &str, name: &str) {
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    if let Some(item) = history.find_mut(folder, name) {
+        item.paste_count += 1;
+        save_history(&history);
+    }
+}
+
+/// Bumps every stored item with the same contents to the top instead of storing a duplicate.
+/// Returns the first bumped item, None when the contents are new
+pub fn touch_by_hash(hash: &str) -> Option<HistoryItem> {
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    let now = get_timestamp_millis();
+    let mut found = None;
+    for item in history.find_by_hash_mut(hash) {
+        item.last_used_at = now;
+        found.get_or_insert(item.clone());
+    }
+
+    if found.is_some() {
+        save_history(&history);
+    }
+
+    found
+}
+
+/// Deletes items whose `expires_at` has passed together with their files and returns them
+pub fn remove_expired() -> Vec<HistoryItem> {
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    let now = get_timestamp_millis();
+    let (expired, kept): (Vec<HistoryItem>, Vec<HistoryItem>) = history
+        .items()
+        .into_iter()
+        .partition(|i| i.expires_at.is_some_and(|at| at <= now));
+    if expired.is_empty() {
+        return expired;
+    }
+
+    history.replace_items(kept);
+    save_history(&history);
+
+    for item in expired.iter() {
+        for file in item.files() {
+            let _ = fs::remove_file(file);
+        }
+    }
+
+    expired
+}
+
+/// Bumps the most recent stored image of the same size which looks like the new one instead of storing a near duplicate
+pub fn touch_similar_image(fingerprint: &Fingerprint, max_distance: u32) -> Option<HistoryItem> {
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    let item = history
+        .items
+        .iter_mut()
+        .filter(|i| i.width == Some(fingerprint.width) && i.height == Some(fingerprint.height))
+        .filter(|i| {
+            i.phash
+                .is_some_and(|p| crate::phash::is_similar(p, fingerprint.dhash, max_distance))
+        })
+        .max_by_key(|i| i.last_used_at)?;
+    item.last_used_at = get_timestamp_millis();
+
+    let item = item.clone();
+    save_history(&history);
+
+    Some(item)
+}
+
+/// Changes a stored item and saves the index, returns the changed item
+pub fn update_item(
+    folder: &str,
+    name: &str,
+    update: impl FnOnce(&mut HistoryItem),
+) -> Option<HistoryItem> {
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    let item = history.find_mut(folder, name)?;
+    update(item);
+
+    let item = item.clone();
+    save_history(&history);
+
+    Some(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesys::FOLDER_CLIPBOARD;
+    use crate::helpers::test_data_dir;
+    use crate::helpers::test_items::text_item;
+
+    fn names(items: &[HistoryItem]) -> Vec<&str> {
+        items.iter().map(|i| i.name.as_str()).collect()
+    }
+
+    #[test]
+    fn evicts_oldest_unpinned_items() {
+        let mut history = History::default();
+        for created_at in 1..=5 {
+            history.add(text_item(FOLDER_CLIPBOARD, created_at, "text"));
+        }
+        history.add(text_item("work", 0, "text"));
+        // recently used, but still captured first
+        history
+            .find_mut(FOLDER_CLIPBOARD, "2.txt")
+            .unwrap()
+            .last_used_at = 100;
+
+        let evicted = history.evict(FOLDER_CLIPBOARD, 3);
+        assert_eq!(names(&evicted), ["1.txt", "2.txt"]);
+        assert_eq!(
+            names(&history.folder_items(FOLDER_CLIPBOARD)),
+            ["5.txt", "4.txt", "3.txt"]
+        );
+        assert!(history.find("work", "0.txt").is_some());
+
+        assert!(history.evict(FOLDER_CLIPBOARD, 3).is_empty());
+    }
+
+    #[test]
+    fn keeps_pinned_items_on_eviction() {
+        let mut history = History::default();
+        for created_at in 1..=4 {
+            history.add(text_item(FOLDER_CLIPBOARD, created_at, "text"));
+        }
+        history.find_mut(FOLDER_CLIPBOARD, "1.txt").unwrap().pinned = true;
+
+        let evicted = history.evict(FOLDER_CLIPBOARD, 2);
+        assert_eq!(names(&evicted), ["2.txt"]);
+        assert_eq!(
+            names(&history.folder_items(FOLDER_CLIPBOARD)),
+            ["1.txt", "4.txt", "3.txt"]
+        );
+
+        // pinned items stay even when nothing else may
+        let evicted = history.evict(FOLDER_CLIPBOARD, 0);
+        assert_eq!(names(&evicted), ["3.txt", "4.txt"]);
+        assert_eq!(names(&history.folder_items(FOLDER_CLIPBOARD)), ["1.txt"]);
+    }
+
+    #[test]
+    fn bumps_every_item_with_the_same_contents() {
+        let _dir = test_data_dir::new("history-touch");
+
+        let copied = text_item(FOLDER_CLIPBOARD, 1, "same");
+        let saved = text_item("work", 2, "same");
+        let other = text_item(FOLDER_CLIPBOARD, 3, "other");
+        let hash = copied.hash.clone();
+        for item in [copied, saved, other] {
+            get_history_instance().lock().add(item);
+        }
+
+        let touched = touch_by_hash(&hash).unwrap();
+        assert_eq!(touched.name, "1.txt");
+        assert!(touched.last_used_at > 3);
+
+        let history = get_history_instance();
+        let history = history.lock();
+        assert_eq!(
+            names(&history.folder_items(FOLDER_CLIPBOARD)),
+            ["1.txt", "3.txt"]
+        );
+        assert_eq!(
+            history.find("work", "2.txt").unwrap().last_used_at,
+            touched.last_used_at
+        );
+        assert_eq!(
+            history
+                .find(FOLDER_CLIPBOARD, "3.txt")
+                .unwrap()
+                .last_used_at,
+            3
+        );
+        drop(history);
+
+        assert!(touch_by_hash(&content_hash(b"new")).is_none());
+    }
+
+    #[test]
+    fn bumps_only_similar_images_of_the_same_size() {
+        let _dir = test_data_dir::new("history-similar");
+
+        let mut image = HistoryItem::new(
+            "1.png".to_string(),
+            FOLDER_CLIPBOARD.to_string(),
+            b"png",
+            formats_for_extension(FileTypes::PNG),
+        );
+        image.phash = Some(0b1011);
+        image.width = Some(640);
+        image.height = Some(400);
+        get_history_instance().lock().add(image);
+
+        let fingerprint = |dhash: u64, height: u32| Fingerprint {
+            dhash,
+            width: 640,
+            height,
+            pixels: 0,
+        };
+
+        assert!(touch_similar_image(&fingerprint(0b1111, 480), 4).is_none());
+        assert!(touch_similar_image(&fingerprint(!0b1011, 400), 4).is_none());
+        assert_eq!(
+            touch_similar_image(&fingerprint(0b1111, 400), 4)
+                .unwrap()
+                .name,
+            "1.png"
+        );
+    }
+
+    #[test]
+    fn reconciles_index_with_files_on_disk() {
+        let _dir = test_data_dir::new("history-load");
+
+        let kept = text_item(FOLDER_CLIPBOARD, 1, "kept");
+        let deleted = text_item(FOLDER_CLIPBOARD, 2, "deleted");
+        fs::create_dir_all(folder_path(FOLDER_CLIPBOARD)).unwrap();
+        fs::write(kept.path(), "kept").unwrap();
+        let mut history = History::default();
+        history.add(kept);
+        history.add(deleted);
+        save_history(&history);
+
+        // saved by an older version, with an html sidecar
+        let dir = folder_path("work");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("1700000000000.txt"), "old").unwrap();
+        fs::write(dir.join("1700000000000.html"), "<i>old</i>").unwrap();
+        fs::write(dir.join("notes.md"), "unknown").unwrap();
+
+        load_history();
+
+        let history = get_history_instance();
+        let history = history.lock();
+        assert_eq!(history.items().len(), 2);
+        assert!(history.find(FOLDER_CLIPBOARD, "1.txt").is_some());
+        assert!(history.find(FOLDER_CLIPBOARD, "2.txt").is_none());
+
+        let old = history.find("work", "1700000000000.txt").unwrap();
+        assert_eq!(old.created_at, 1_700_000_000_000);
+        assert_eq!(old.last_used_at, 1_700_000_000_000);
+        assert_eq!(old.formats, [MimeTypes::TEXT_PLAIN]);
+        assert_eq!(old.hash, content_hash(b"old"));
+        assert_eq!(old.size, 3);
+        drop(history);
+
+        // the rebuilt index is saved
+        let saved = read_index().unwrap();
+        assert_eq!(saved.items().len(), 2);
+    }
+}
@@ -5,11 +5,8 @@ use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock};
 use std::thread;
-use std::thread::sleep;
-use std::time::Duration;
 use parking_lot::lock_api::MutexGuard;
-use crate::clipboard::my_clipboard;
-use crate::processes::app_active_state;
+use crate::paste_queue;
 use crate::settings::get_settings_instance;
 use crate::window;
 
@@ -176,20 +173,24 @@ fn handle_hotkeys(mut hotkeys_listener: MutexGuard<RawMutex, HotkeysListener>) {
         ),
     }
 
-    // TODO: test/fix bind on linux/mac
-    let copy_to_clipboard_hotkeys = vec![Keycode::LControl, Keycode::C];
-    hotkeys_listener.subscribe(
-        Hotkeys::new(copy_to_clipboard_hotkeys),
-        Box::new(|| {
-            if !app_active_state() {
-                return;
-            }
-            // without sleep we get access to prev clipboard data
-            sleep(Duration::from_millis(100));
+    match parse_keycodes(settings.paste_queue_hotkey.clone()) {
+        Ok(hotkeys) => {
+            hotkeys_listener.subscribe(
+                Hotkeys::new(hotkeys),
+                Box::new(|| {
+                    if !paste_queue::is_enabled() {
+                        return;
+                    }
 
-            my_clipboard::text::on_copy();
-        }),
-    );
+                    thread::spawn(paste_queue::paste_next);
+                }),
+            );
+        }
+        Err(err) => println!(
+            "Error parsing hotkeys {:#?}: {}",
+            settings.paste_queue_hotkey, err
+        ),
+    }
 }
 
 pub fn parse_keycodes(input: String) -> Result<Vec<Keycode>, String> {
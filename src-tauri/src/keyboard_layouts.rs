@@ -1,37 +1,56 @@
+#[cfg(target_os = "windows")]
 use std::ffi::OsString;
+#[cfg(target_os = "windows")]
 use std::os::windows::ffi::OsStringExt;
+#[cfg(target_os = "windows")]
 use std::{ptr};
 use std::sync::{Arc, OnceLock};
 use parking_lot::Mutex;
+#[cfg(target_os = "windows")]
 use winapi::ctypes::c_int;
+#[cfg(target_os = "windows")]
 use winapi::shared::minwindef::{HKL, LOWORD};
+#[cfg(target_os = "windows")]
 use winapi::um::winnls::{GetLocaleInfoW, LCIDToLocaleName};
+#[cfg(target_os = "windows")]
 use winapi::um::winnt::{LOCALE_NAME_MAX_LENGTH};
+#[cfg(target_os = "windows")]
 use winapi::um::winuser::{GetKeyboardLayoutList, GetWindowThreadProcessId};
+#[cfg(target_os = "windows")]
 use winapi::um::winuser::{GetForegroundWindow, GetKeyboardLayout};
-use winapi::um::winnt::LANGID;
+#[cfg(target_os = "windows")]
 use winapi::shared::windef::HWND;
+#[cfg(target_os = "windows")]
 use winapi::um::winuser::LoadKeyboardLayoutW;
+#[cfg(target_os = "windows")]
 use winapi::um::winuser::KLF_ACTIVATE;
+#[cfg(target_os = "windows")]
 use winapi::um::winuser::PostMessageW;
+#[cfg(target_os = "windows")]
 use winapi::um::winuser::WM_INPUTLANGCHANGEREQUEST;
+#[cfg(target_os = "windows")]
 use winapi::shared::minwindef::LPARAM;
+#[cfg(target_os = "windows")]
 use crate::helpers;
 use serde::{Deserialize, Serialize};
 use crate::filesys::{read_json_data, write_json_data, FILENAME_KEYBOARD_LAYOUTS};
 
-/// TODO: add linux/macos
+// Layouts are only listed and switched on Windows, other platforms report the system locale
+
+/// Windows LANGID, e.g. 1033 for en-US
+pub type LangId = u16;
 
 pub const LOCALE_SNATIVELANGNAME: u32 = 4;
 pub const LOCALE_SENGLANGUAGE: u32 = 4097;
 
 #[derive(Debug, Serialize)]
 pub struct KeyboardLayout {
-    pub lang_id: LANGID,
+    pub lang_id: LangId,
     pub lang_code: String,
     pub lang_name: String,
 }
 
+#[cfg(target_os = "windows")]
 impl KeyboardLayout {
     pub fn from_handle(hkl: u16) -> Self {
         KeyboardLayout {
@@ -42,7 +61,7 @@ impl KeyboardLayout {
     }
 
     // 1033 -> "en-US"
-    fn langid_to_locale(langid: LANGID) -> String {
+    fn langid_to_locale(langid: LangId) -> String {
         let mut buffer = [0u16; LOCALE_NAME_MAX_LENGTH as usize];
         let result = unsafe {
             LCIDToLocaleName(
@@ -57,7 +76,7 @@ impl KeyboardLayout {
     }
 
     // 1033 -> "English"
-    fn langid_to_lang_name(langid: LANGID) -> String {
+    fn langid_to_lang_name(langid: LangId) -> String {
         let mut buffer = [0u16; LOCALE_NAME_MAX_LENGTH as usize];
         let result = unsafe {
             GetLocaleInfoW(
@@ -82,6 +101,7 @@ impl KeyboardLayout {
 }
 
 // -> 1033
+#[cfg(target_os = "windows")]
 pub fn get_current_keyboard_lang_id() -> u16 {
     unsafe {
         let foreground_window = GetForegroundWindow();
@@ -92,11 +112,19 @@ pub fn get_current_keyboard_lang_id() -> u16 {
 }
 
 // -> "en-US"
+#[cfg(target_os = "windows")]
 pub fn get_current_keyboard_layout_locale() -> String {
     let lang_id =  get_current_keyboard_lang_id();
     KeyboardLayout::langid_to_locale(lang_id)
 }
 
+// -> "en-US", layout of the focused window isn't available, the system locale is used
+#[cfg(not(target_os = "windows"))]
+pub fn get_current_keyboard_layout_locale() -> String {
+    sys_locale::get_locale().unwrap_or_else(|| "en-US".to_string())
+}
+
+#[cfg(target_os = "windows")]
 #[tauri::command]
 pub fn get_available_keyboard_layouts() -> Result<Vec<KeyboardLayout>, String> {
     let count = unsafe { GetKeyboardLayoutList(0, ptr::null_mut()) };
@@ -117,6 +145,13 @@ pub fn get_available_keyboard_layouts() -> Result<Vec<KeyboardLayout>, String> {
         .collect())
 }
 
+#[cfg(not(target_os = "windows"))]
+#[tauri::command]
+pub fn get_available_keyboard_layouts() -> Result<Vec<KeyboardLayout>, String> {
+    Ok(vec![])
+}
+
+#[cfg(target_os = "windows")]
 pub fn change_keyboard_layout(window_handle: HWND, lang_code: u16) {
     if lang_code == get_current_keyboard_lang_id() {
         return;
@@ -1,17 +1,37 @@
 extern crate core;
 
 pub mod keys;
+pub mod archive;
 pub mod auto_replacement;
 pub mod autorun;
 pub mod clipboard;
+pub mod clipboard_hints;
+pub mod clipboard_monitor;
+#[cfg(target_os = "linux")]
+pub mod clipboard_persistence;
 pub mod common;
+pub mod content_kind;
+pub mod crypto;
 pub mod filesys;
+pub mod folders;
 pub mod helpers;
+pub mod history;
 pub mod hotkeys_listener;
 pub mod hotkeys_reader;
 pub mod keyboard_layouts;
+pub mod paste_queue;
+pub mod phash;
 pub mod processes;
+pub mod retention;
+pub mod search;
+pub mod sensitive;
 pub mod settings;
+pub mod templates;
+pub mod thumbnails;
+pub mod transformations;
 pub mod tray;
+#[cfg(target_os = "windows")]
 pub mod win_key_hook;
 pub mod window;
+#[cfg(target_os = "linux")]
+pub mod x11;
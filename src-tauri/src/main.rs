@@ -4,7 +4,7 @@
 )]
 
 use app::helpers::APP_HANDLE;
-use app::{auto_replacement, clipboard as my_clipboard, filesys, hotkeys_reader, keyboard_layouts, processes, settings, tray, win_key_hook, window};
+use app::{archive, auto_replacement, paste_queue, clipboard as my_clipboard, crypto, filesys, folders, hotkeys_reader, keyboard_layouts, processes, search, settings, tray, window};
 use std::thread;
 use tauri::Manager;
 
@@ -35,22 +35,44 @@ fn main() {
                 processes::watch_active_window();
             });
 
+            #[cfg(target_os = "windows")]
             thread::spawn(|| unsafe {
-                win_key_hook::win_key_hook();
+                app::win_key_hook::win_key_hook();
             });
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             filesys::remove_clipboard_item,
+            filesys::update_clipboard_item,
             filesys::move_clipboard_item,
+            filesys::pin_clipboard_item,
+            filesys::read_full_image,
             filesys::delete_all_by_folder,
             filesys::read_clipboard_data,
+            search::search_clipboard,
+            archive::export_data,
+            archive::import_data,
+            crypto::encryption_status,
+            crypto::enable_encryption,
+            crypto::disable_encryption,
+            crypto::unlock_history,
+            crypto::lock_history,
             window::hide_window,
             window::show_window,
             window::quit,
             my_clipboard::paste,
+            my_clipboard::paste_transformed,
+            paste_queue::set_paste_queue,
+            paste_queue::clear_paste_queue,
+            paste_queue::paste_queue_status,
             auto_replacement::update_auto_replace_data,
+            folders::get_folders,
+            folders::create_folder,
+            folders::rename_folder,
+            folders::delete_folder,
+            folders::update_folder_settings,
+            folders::update_folders_data,
             processes::get_proccesses_list,
             processes::update_blacklist_data,
             hotkeys_reader::hotkeys_listen,
@@ -0,0 +1,270 @@
+use crate::clipboard;
+use crate::helpers::get_tauri_handle;
+use crate::history::HistoryItem;
+use device_query::{DeviceQuery, DeviceState};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+pub const TRAY_ITEM_PASTE_QUEUE: &str = "paste_queue";
+
+/// Paste hotkey modifiers are still held when it fires, they'd turn Ctrl+V into something else
+const KEYS_RELEASE_TIMEOUT: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+struct QueuedItem {
+    folder: String,
+    name: String,
+}
+
+/// Captures made while enabled, pasted one by one with the paste queue hotkey
+#[derive(Debug, Default)]
+pub struct PasteQueue {
+    enabled: bool,
+    items: VecDeque<QueuedItem>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+struct PasteQueueStatus {
+    enabled: bool,
+    remaining: usize,
+}
+
+impl PasteQueue {
+    /// Appends the item while queue mode is enabled, returns whether it was queued
+    fn push(&mut self, item: &HistoryItem) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        self.items.push_back(QueuedItem {
+            folder: item.folder.clone(),
+            name: item.name.clone(),
+        });
+
+        true
+    }
+
+    /// Oldest queued item, removed from queue
+    fn pop(&mut self) -> Option<QueuedItem> {
+        self.items.pop_front()
+    }
+
+    /// The queue starts empty every time it's enabled or disabled
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.items.clear();
+    }
+
+    fn status(&self) -> PasteQueueStatus {
+        PasteQueueStatus {
+            enabled: self.enabled,
+            remaining: self.items.len(),
+        }
+    }
+}
+
+pub static PASTE_QUEUE: OnceLock<Arc<Mutex<PasteQueue>>> = OnceLock::new();
+
+pub fn get_paste_queue_instance() -> Arc<Mutex<PasteQueue>> {
+    PASTE_QUEUE
+        .get_or_init(|| Arc::new(Mutex::new(PasteQueue::default())))
+        .clone()
+}
+
+pub fn is_enabled() -> bool {
+    get_paste_queue_instance().lock().enabled
+}
+
+fn emit_status(queue: &PasteQueue) {
+    get_tauri_handle().emit_all("paste_queue", queue.status()).unwrap();
+}
+
+/// Appends a captured item while queue mode is enabled
+pub fn push(item: &HistoryItem) {
+    let queue = get_paste_queue_instance();
+    let mut queue = queue.lock();
+
+    if queue.push(item) {
+        emit_status(&queue);
+    }
+}
+
+/// Pastes the oldest queued item and removes it from queue
+pub fn paste_next() {
+    let next = {
+        let queue = get_paste_queue_instance();
+        let mut queue = queue.lock();
+
+        let next = queue.pop();
+        emit_status(&queue);
+
+        next
+    };
+
+    let Some(next) = next else {
+        return;
+    };
+
+    let device_state = DeviceState::new();
+    let deadline = Instant::now() + Duration::from_millis(KEYS_RELEASE_TIMEOUT);
+    while !device_state.get_keys().is_empty() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let app = get_tauri_handle().clone();
+    let result = tauri::async_runtime::block_on(clipboard::paste_item(
+        &next.folder,
+        &next.name,
+        &[],
+        app,
+    ));
+
+    if let Err(e) = result {
+        eprintln!("paste_queue: {}", e);
+    }
+}
+
+fn set_enabled(enabled: bool) {
+    let queue = get_paste_queue_instance();
+    let mut queue = queue.lock();
+
+    queue.set_enabled(enabled);
+
+    let title = if enabled {
+        "Stop paste queue"
+    } else {
+        "Start paste queue"
+    };
+    let _ = get_tauri_handle()
+        .tray_handle()
+        .get_item(TRAY_ITEM_PASTE_QUEUE)
+        .set_title(title);
+
+    emit_status(&queue);
+}
+
+pub fn toggle() {
+    set_enabled(!is_enabled());
+}
+
+/// Enables queue mode, the queue starts empty every time
+#[tauri::command]
+pub fn set_paste_queue(enabled: bool) {
+    set_enabled(enabled);
+}
+
+#[tauri::command]
+pub fn clear_paste_queue() {
+    let queue = get_paste_queue_instance();
+    let mut queue = queue.lock();
+
+    queue.items.clear();
+    emit_status(&queue);
+}
+
+#[tauri::command]
+pub fn paste_queue_status() -> Result<String, String> {
+    let queue = get_paste_queue_instance();
+    let queue = queue.lock();
+
+    serde_json::to_string(&queue.status()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesys::FOLDER_CLIPBOARD;
+    use crate::helpers::test_items::text_item;
+
+    fn queued(item: &HistoryItem) -> Option<QueuedItem> {
+        Some(QueuedItem {
+            folder: item.folder.clone(),
+            name: item.name.clone(),
+        })
+    }
+
+    #[test]
+    fn pastes_items_in_capture_order() {
+        let mut queue = PasteQueue::default();
+        queue.set_enabled(true);
+
+        let items: Vec<_> = (1..=3)
+            .map(|created_at| text_item(FOLDER_CLIPBOARD, created_at, "text"))
+            .collect();
+        for item in &items {
+            assert!(queue.push(item));
+        }
+
+        for item in &items {
+            assert_eq!(queue.pop(), queued(item));
+        }
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn dequeues_each_item_once() {
+        let mut queue = PasteQueue::default();
+        queue.set_enabled(true);
+        let first = text_item(FOLDER_CLIPBOARD, 1, "first");
+        let second = text_item("work", 2, "second");
+        queue.push(&first);
+        queue.push(&second);
+
+        assert_eq!(queue.pop(), queued(&first));
+        assert_eq!(queue.status().remaining, 1);
+
+        // items captured after some were pasted go to the end
+        let third = text_item(FOLDER_CLIPBOARD, 3, "third");
+        queue.push(&third);
+        assert_eq!(queue.pop(), queued(&second));
+        assert_eq!(queue.pop(), queued(&third));
+        assert_eq!(queue.status().remaining, 0);
+    }
+
+    #[test]
+    fn queues_only_while_enabled() {
+        let mut queue = PasteQueue::default();
+        let item = text_item(FOLDER_CLIPBOARD, 1, "text");
+
+        assert!(!queue.push(&item));
+        assert_eq!(queue.pop(), None);
+
+        queue.set_enabled(true);
+        assert!(queue.push(&item));
+
+        queue.set_enabled(false);
+        assert!(!queue.push(&item));
+        assert_eq!(
+            queue.status(),
+            PasteQueueStatus {
+                enabled: false,
+                remaining: 0
+            }
+        );
+    }
+
+    #[test]
+    fn starts_empty_when_toggled() {
+        let mut queue = PasteQueue::default();
+        queue.set_enabled(true);
+        queue.push(&text_item(FOLDER_CLIPBOARD, 1, "text"));
+        queue.push(&text_item(FOLDER_CLIPBOARD, 2, "text"));
+
+        queue.set_enabled(false);
+        queue.set_enabled(true);
+
+        assert_eq!(
+            queue.status(),
+            PasteQueueStatus {
+                enabled: true,
+                remaining: 0
+            }
+        );
+        assert_eq!(queue.pop(), None);
+    }
+}
@@ -0,0 +1,213 @@
+use image::{imageops, GenericImageView, ImageBuffer, Pixel, Rgba};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// What a captured image is compared by to tell whether it was seen before
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fingerprint {
+    pub dhash: u64,
+    pub width: u32,
+    pub height: u32,
+    /// hash of the raw pixels, equal for identical images only
+    pub pixels: u64,
+}
+
+impl Fingerprint {
+    /// None if the buffer doesn't match the size
+    pub fn of_rgba(width: u32, height: u32, bytes: &[u8]) -> Option<Self> {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+
+        Some(Fingerprint {
+            dhash: dhash_rgba(width, height, bytes)?,
+            width,
+            height,
+            pixels: hasher.finish(),
+        })
+    }
+
+    /// Identical images always match, with `max_distance` above 0 so do images of the same size whose
+    /// dHashes are that close. Sizes must match: scaled down to 9x8, different screenshots of text look alike
+    pub fn matches(&self, other: &Fingerprint, max_distance: u32) -> bool {
+        let same_size = self.width == other.width && self.height == other.height;
+
+        same_size
+            && (self.pixels == other.pixels
+                || (max_distance > 0 && is_similar(self.dhash, other.dhash, max_distance)))
+    }
+}
+
+/// dHash of an image: 64 bits, one per comparison of horizontally adjacent pixels of a 9x8 grayscale copy.
+/// Re-encoded or slightly changed images get the same or a close hash
+pub fn dhash<I: GenericImageView<Pixel = Rgba<u8>>>(image: &I) -> u64 {
+    let small = imageops::thumbnail(image, 9, 8);
+    let luma = |x: u32, y: u32| small.get_pixel(x, y).to_luma()[0];
+
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if luma(x, y) < luma(x + 1, y) {
+                hash |= 1;
+            }
+        }
+    }
+
+    hash
+}
+
+/// dHash of raw RGBA pixels, None if the buffer doesn't match the size
+pub fn dhash_rgba(width: u32, height: u32, bytes: &[u8]) -> Option<u64> {
+    let buffer: ImageBuffer<Rgba<u8>, &[u8]> = ImageBuffer::from_raw(width, height, bytes)?;
+
+    Some(dhash(&buffer))
+}
+
+/// Number of differing bits, 0 means the images look the same
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Whether hashes differ by `max_distance` bits at most, 0 accepts equal hashes only
+pub fn is_similar(a: u64, b: u64, max_distance: u32) -> bool {
+    distance(a, b) <= max_distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::DEFAULT_IMAGE_SIMILARITY;
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+    use std::io::Cursor;
+
+    /// Distance a user would set to catch re-encoded images
+    const SIMILARITY: u32 = 4;
+
+    /// Soft diagonal waves, like a photo rather than flat areas where neighbours compare equal
+    fn waves(width: u32, height: u32, phase: f32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let x = x as f32 / width as f32 * 6.0;
+            let y = y as f32 / height as f32 * 4.0;
+            let value = 128.0 + 60.0 * (x + phase).sin() + 60.0 * (y * 1.7 - phase).cos();
+            let value = value as u8;
+
+            Rgba([value, value / 2, 255 - value, 255])
+        })
+    }
+
+    /// Dark blocks where letters of `lines` are, like a screenshot of an editor
+    fn text_screenshot(width: u32, height: u32, lines: &[&str]) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let (column, row) = ((x / 8) as usize, (y / 16) as usize);
+            let letter = lines.get(row).and_then(|line| line.as_bytes().get(column));
+            // glyphs differ by which of their pixels are inked
+            let inked = letter.is_some_and(|c| {
+                *c != b' ' && x % 8 < 6 && y % 16 < 12 && (*c as u32 >> ((x + y) % 7)) & 1 == 1
+            });
+
+            match inked {
+                true => Rgba([30, 30, 30, 255]),
+                false => Rgba([250, 250, 250, 255]),
+            }
+        })
+    }
+
+    fn fingerprint(image: &RgbaImage) -> Fingerprint {
+        Fingerprint::of_rgba(image.width(), image.height(), image.as_raw()).unwrap()
+    }
+
+    fn reencode(image: &RgbaImage, format: ImageFormat) -> RgbaImage {
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image.clone())
+            .to_rgb8()
+            .write_to(&mut bytes, format)
+            .unwrap();
+
+        image::load_from_memory(bytes.get_ref()).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn same_image_has_same_hash() {
+        let image = waves(320, 240, 0.0);
+
+        assert_eq!(dhash(&image), dhash(&image.clone()));
+        assert_eq!(dhash_rgba(320, 240, image.as_raw()), Some(dhash(&image)));
+        assert_eq!(dhash_rgba(320, 241, image.as_raw()), None);
+    }
+
+    #[test]
+    fn reencoded_and_resized_images_are_similar() {
+        let image = waves(320, 240, 0.0);
+        let hash = dhash(&image);
+
+        let jpeg = reencode(&image, ImageFormat::Jpeg);
+        assert!(is_similar(hash, dhash(&jpeg), SIMILARITY));
+
+        let smaller = imageops::resize(&image, 160, 120, imageops::FilterType::Triangle);
+        assert!(is_similar(hash, dhash(&smaller), SIMILARITY));
+    }
+
+    #[test]
+    fn distinct_images_are_not_similar() {
+        let hash = dhash(&waves(320, 240, 0.0));
+        let other = dhash(&waves(320, 240, 2.5));
+
+        assert!(distance(hash, other) > SIMILARITY * 4);
+        assert!(!is_similar(hash, other, SIMILARITY));
+    }
+
+    #[test]
+    fn threshold_is_inclusive() {
+        assert_eq!(distance(0, u64::MAX), 64);
+
+        assert!(is_similar(0b1011, 0b1011, 0));
+        assert!(!is_similar(0b1011, 0b1010, 0));
+
+        assert!(is_similar(0, 0b1111, 4));
+        assert!(!is_similar(0, 0b11111, 4));
+        assert!(is_similar(0, u64::MAX, 64));
+    }
+
+    #[test]
+    fn identical_images_match_without_similarity() {
+        let image = waves(320, 240, 0.0);
+
+        assert!(fingerprint(&image).matches(&fingerprint(&image.clone()), 0));
+
+        let jpeg = fingerprint(&reencode(&image, ImageFormat::Jpeg));
+        assert!(!fingerprint(&image).matches(&jpeg, 0));
+        assert!(fingerprint(&image).matches(&jpeg, SIMILARITY));
+    }
+
+    #[test]
+    fn different_text_screenshots_do_not_match() {
+        let first = text_screenshot(
+            640,
+            400,
+            &[
+                "fn main() {",
+                "    let total = 42;",
+                "    println!(\"{}\", total);",
+                "}",
+            ],
+        );
+        let second = text_screenshot(
+            640,
+            400,
+            &[
+                "use std::fs;",
+                "",
+                "fn read() -> String {",
+                "    fs::read_to_string(\"a\").unwrap()",
+                "}",
+            ],
+        );
+
+        // their dHashes are close, which is why similarity is off by default
+        assert!(!fingerprint(&first).matches(&fingerprint(&second), DEFAULT_IMAGE_SIMILARITY));
+
+        // the same text in a bigger window looks alike once scaled down, the size tells them apart
+        let taller = text_screenshot(640, 480, &["fn main() {", "    let total = 42;"]);
+        let shorter = text_screenshot(640, 400, &["fn main() {", "    let total = 42;"]);
+        assert!(!fingerprint(&taller).matches(&fingerprint(&shorter), SIMILARITY));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::filesys::{read_json_data, write_json_data, FILENAME_APPS_BLACKLIST};
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 use core::time;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
@@ -6,24 +7,55 @@ use std::collections::HashSet;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock};
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 use std::thread;
 use sysinfo::{Pid, RefreshKind, System};
+#[cfg(target_os = "windows")]
 use winapi::shared::minwindef::{BOOL, LPARAM};
+#[cfg(target_os = "windows")]
 use winapi::shared::windef::{HWND, RECT};
+#[cfg(target_os = "windows")]
 use winapi::um::winuser::{EnumWindows, GetAncestor, GetDesktopWindow, GetForegroundWindow, GetShellWindow, GetSystemMetrics, GetWindowRect, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible, GA_ROOTOWNER, SM_CXSCREEN, SM_CYSCREEN};
-use crate::keyboard_layouts::{change_keyboard_layout, get_key_apps_instance, is_key_apps_empty, update_keyboard_layouts_data};
+#[cfg(target_os = "windows")]
+use crate::keyboard_layouts::{change_keyboard_layout, get_key_apps_instance, is_key_apps_empty};
+use crate::keyboard_layouts::update_keyboard_layouts_data;
+#[cfg(target_os = "linux")]
+use crate::x11::X11Windows;
 
 // TODO: refactor to use interface-like implementation to have same pub funcs for other os
 
-/// Used throughout the app to check if app is currently enabled
-static IS_APP_ACTIVE: AtomicBool = AtomicBool::new(true);
+/// Parts of the app which can be turned off while a blacklisted app is in foreground
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    Clipboard,
+    AutoReplacement,
+    WinKey,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 3] = [Feature::Clipboard, Feature::AutoReplacement, Feature::WinKey];
+
+    fn flag(&self) -> &'static AtomicBool {
+        match self {
+            Feature::Clipboard => &IS_CLIPBOARD_ACTIVE,
+            Feature::AutoReplacement => &IS_AUTO_REPLACEMENT_ACTIVE,
+            Feature::WinKey => &IS_WIN_KEY_ACTIVE,
+        }
+    }
+}
+
+/// Checked by each feature before it does anything, see `handle_blacklist_window`
+static IS_CLIPBOARD_ACTIVE: AtomicBool = AtomicBool::new(true);
+static IS_AUTO_REPLACEMENT_ACTIVE: AtomicBool = AtomicBool::new(true);
+static IS_WIN_KEY_ACTIVE: AtomicBool = AtomicBool::new(true);
 
-pub fn app_active_state() -> bool {
-    IS_APP_ACTIVE.load(Ordering::Relaxed)
+pub fn feature_active(feature: Feature) -> bool {
+    feature.flag().load(Ordering::Relaxed)
 }
 
-pub fn set_app_active_state(state: bool) {
-    IS_APP_ACTIVE.store(state, Ordering::Relaxed); // 200 ns
+pub fn set_feature_active(feature: Feature, state: bool) {
+    feature.flag().store(state, Ordering::Relaxed);
 }
 
 /// Used throughout the app to check if app is fullscreen
@@ -39,10 +71,47 @@ pub fn set_is_fg_window_fullscreen(state: bool) {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MyProcess {
-    pid: u32,
-    title: String,
-    filename: String,
-    filepath: String,
+    pub pid: u32,
+    pub title: String,
+    pub filename: String,
+    pub filepath: String,
+}
+
+/// Foreground process seen by `watch_active_window`, recorded as the source of captured items
+static ACTIVE_PROCESS: OnceLock<Arc<Mutex<Option<MyProcess>>>> = OnceLock::new();
+
+pub fn get_active_process_instance() -> Arc<Mutex<Option<MyProcess>>> {
+    ACTIVE_PROCESS
+        .get_or_init(|| Arc::new(Mutex::new(None)))
+        .clone()
+}
+
+pub fn active_process() -> Option<MyProcess> {
+    get_active_process_instance().lock().clone()
+}
+
+/// Source of a captured item, the foreground process is looked up at copy time,
+/// the 500 ms sample could still be the app focused before
+pub fn source_process() -> Option<MyProcess> {
+    foreground_process().or_else(active_process)
+}
+
+fn process_by_pid(sys: &mut System, pid: u32, title: String) -> Option<MyProcess> {
+    let pid = Pid::from_u32(pid);
+    sys.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]));
+    let p = sys.process(pid)?;
+
+    Some(MyProcess {
+        pid: pid.as_u32(),
+        title,
+        filename: p.name().to_string_lossy().to_string(),
+        filepath: p
+            .exe()
+            .unwrap_or(Path::new(""))
+            .to_owned()
+            .to_string_lossy()
+            .to_string(),
+    })
 }
 
 #[allow(dead_code)]
@@ -52,12 +121,24 @@ pub struct BlacklistItem {
     pub enabled: bool,
     pub filename: Option<String>,
     pub title: Option<String>,
+    /// entries saved before features could be picked disable everything
+    #[serde(default = "default_disabled_features")]
+    pub disabled_features: HashSet<Feature>,
+    /// features which work only while one of the apps listing them is in foreground
+    #[serde(default)]
+    pub only_features: HashSet<Feature>,
+}
+
+fn default_disabled_features() -> HashSet<Feature> {
+    HashSet::from(Feature::ALL)
 }
 
+#[cfg(target_os = "windows")]
 pub struct SystemProcesses {
     sys: System,
 }
 
+#[cfg(target_os = "windows")]
 impl SystemProcesses {
     fn new() -> Self {
         SystemProcesses {
@@ -90,6 +171,7 @@ impl SystemProcesses {
     }
 }
 
+#[cfg(target_os = "windows")]
 pub unsafe fn watch_active_window() {
     let _ = update_blacklist_data();
     let _ = update_keyboard_layouts_data();
@@ -111,6 +193,8 @@ pub unsafe fn watch_active_window() {
         if let Some(current_process) = active_window(&mut system_processes, Some(hwnd)) {
             // println!("------ {:#?}", current_process);
 
+            *get_active_process_instance().lock() = Some(current_process.clone());
+
             handle_blacklist_window(&current_process);
 
             handle_full_screen_app(hwnd, &current_process);
@@ -120,6 +204,7 @@ pub unsafe fn watch_active_window() {
     }
 }
 
+#[cfg(target_os = "windows")]
 unsafe fn handle_keyboard_layout(hwnd: HWND, current_process: &MyProcess) {
     if is_key_apps_empty() {
         return;
@@ -148,19 +233,29 @@ unsafe fn handle_blacklist_window(current_process: &MyProcess) {
     let blacklist = get_blacklist_instance(); // 2µs
     let blacklist = blacklist.lock();
 
-    if let Some(blacklisted) = blacklist
-        .iter()
-        .find(|bl| bl.filepath == current_process.filepath)
-    {
-        if blacklisted.enabled {
-            println!("Blacklisted app: {:?}", blacklisted.filepath);
-            set_app_active_state(false);
+    for feature in Feature::ALL {
+        let active = is_feature_allowed(&blacklist, feature, &current_process.filepath);
+        if feature_active(feature) != active {
+            let state = if active { "enabled" } else { "disabled" };
+            println!("{:?} {} for {:?}", feature, state, current_process.filepath);
+            set_feature_active(feature, active);
         }
-    } else {
-        set_app_active_state(true);
     }
 }
 
+/// Whether `feature` should work while the app at `filepath` is in foreground:
+/// only in the apps listing it in `only_features` if there are any, otherwise unless the app disables it
+fn is_feature_allowed(blacklist: &[BlacklistItem], feature: Feature, filepath: &str) -> bool {
+    let mut enabled = blacklist.iter().filter(|bl| bl.enabled);
+
+    if enabled.clone().any(|bl| bl.only_features.contains(&feature)) {
+        return enabled.any(|bl| bl.filepath == filepath && bl.only_features.contains(&feature));
+    }
+
+    !enabled.any(|bl| bl.filepath == filepath && bl.disabled_features.contains(&feature))
+}
+
+#[cfg(target_os = "windows")]
 unsafe fn handle_full_screen_app(hwnd: HWND, process: &MyProcess) {
     if !cfg!(target_os = "windows") {
         return;
@@ -200,6 +295,10 @@ fn set_blacklist_data(new_data: Option<Vec<BlacklistItem>>) -> Vec<BlacklistItem
     let mut blacklist = blacklist.lock();
 
     if let Some(data) = new_data {
+        // the window check is skipped for an empty blacklist, features turned off before would stay off
+        if data.is_empty() {
+            Feature::ALL.iter().for_each(|&f| set_feature_active(f, true));
+        }
         *blacklist = data.clone();
     }
 
@@ -224,6 +323,7 @@ pub fn update_blacklist_data() -> Result<(), String> {
     }
 }
 
+#[cfg(target_os = "windows")]
 pub unsafe fn active_window(
     system_processes: &mut SystemProcesses,
     window: Option<HWND>,
@@ -250,7 +350,10 @@ pub fn processes() -> Vec<MyProcess> {
 
     let mut list: Vec<MyProcess> = Vec::new();
     for (pid, title) in foreground_apps().iter() {
-        let p = processes.get(&Pid::from_u32(*pid)).unwrap();
+        // windows of sandboxed apps report pids of their own namespace
+        let Some(p) = processes.get(&Pid::from_u32(*pid)) else {
+            continue;
+        };
         list.push(MyProcess {
             pid: *pid,
             title: title.clone(),
@@ -274,6 +377,7 @@ pub async fn get_proccesses_list() -> Result<String, String> {
     Ok(serde_json::to_string(&data).unwrap_or("oops".to_string()))
 }
 
+#[cfg(target_os = "windows")]
 unsafe fn window_text(hwnd: HWND) -> String {
     let length = GetWindowTextLengthW(hwnd) + 1;
     let mut buffer: Vec<u16> = vec![0; length as usize];
@@ -283,6 +387,7 @@ unsafe fn window_text(hwnd: HWND) -> String {
     window_title
 }
 
+#[cfg(target_os = "windows")]
 unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
     let process_ids = &mut *(lparam as *mut HashSet<(u32, String)>);
 
@@ -299,6 +404,29 @@ unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL
     1 // continue enumeration
 }
 
+/// Process of the foreground window, the root owner of dialogs and popups
+#[cfg(target_os = "windows")]
+fn foreground_process() -> Option<MyProcess> {
+    unsafe {
+        let hwnd: HWND = GetForegroundWindow();
+        if hwnd.is_null() {
+            return None;
+        }
+
+        let hwnd = GetAncestor(hwnd, GA_ROOTOWNER);
+        if hwnd.is_null() {
+            return None;
+        }
+
+        let mut pid: u32 = 0;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+
+        process_by_pid(&mut System::new(), pid, window_text(hwnd))
+    }
+}
+
+/// Pids and titles of visible windows
+#[cfg(target_os = "windows")]
 fn foreground_apps() -> HashSet<(u32, String)> {
     let mut foreground_apps: HashSet<(u32, String)> = HashSet::new();
     unsafe {
@@ -340,3 +468,135 @@ pub unsafe fn is_fullscreen(hwnd: HWND) -> bool {
 pub fn is_fullscreen() -> bool {
     false
 }
+
+/// Tracks the window the window manager reports as active, fullscreen and keyboard layouts are Windows only
+///
+/// # Safety
+/// Nothing unsafe, it's only marked so to match the Windows version
+#[cfg(target_os = "linux")]
+pub unsafe fn watch_active_window() {
+    let _ = update_blacklist_data();
+    let _ = update_keyboard_layouts_data();
+
+    let windows = match X11Windows::new() {
+        Ok(windows) => windows,
+        Err(e) => {
+            eprintln!("watch_active_window: {}", e);
+            return;
+        }
+    };
+    let mut sys = System::new();
+
+    loop {
+        thread::sleep(time::Duration::from_millis(500));
+
+        if let Some(current_process) = x11_active_process(&windows, &mut sys) {
+            *get_active_process_instance().lock() = Some(current_process.clone());
+
+            handle_blacklist_window(&current_process);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn x11_active_process(windows: &X11Windows, sys: &mut System) -> Option<MyProcess> {
+    let window = windows.active_window()?;
+
+    process_by_pid(sys, windows.pid(window)?, windows.title(window))
+}
+
+#[cfg(target_os = "linux")]
+fn foreground_process() -> Option<MyProcess> {
+    x11_active_process(&X11Windows::new().ok()?, &mut System::new())
+}
+
+/// Pids and titles of windows managed by the window manager
+#[cfg(target_os = "linux")]
+fn foreground_apps() -> HashSet<(u32, String)> {
+    let Ok(windows) = X11Windows::new() else {
+        return HashSet::new();
+    };
+
+    windows
+        .client_list()
+        .into_iter()
+        .filter_map(|window| Some((windows.pid(window)?, windows.title(window))))
+        .collect()
+}
+
+/// Foreground window isn't tracked yet, only the lists are loaded
+///
+/// # Safety
+/// Nothing unsafe, it's only marked so to match the Windows version
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub unsafe fn watch_active_window() {
+    let _ = update_blacklist_data();
+    let _ = update_keyboard_layouts_data();
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn foreground_process() -> Option<MyProcess> {
+    None
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn foreground_apps() -> HashSet<(u32, String)> {
+    HashSet::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(filepath: &str, disabled: &[Feature], only: &[Feature]) -> BlacklistItem {
+        BlacklistItem {
+            filepath: filepath.to_string(),
+            enabled: true,
+            filename: None,
+            title: None,
+            disabled_features: disabled.iter().copied().collect(),
+            only_features: only.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn disables_features_in_blacklisted_apps() {
+        let blacklist = [app("/usr/bin/game", &[Feature::Clipboard], &[])];
+
+        assert!(!is_feature_allowed(&blacklist, Feature::Clipboard, "/usr/bin/game"));
+        assert!(is_feature_allowed(&blacklist, Feature::WinKey, "/usr/bin/game"));
+        assert!(is_feature_allowed(&blacklist, Feature::Clipboard, "/usr/bin/editor"));
+    }
+
+    #[test]
+    fn allows_features_only_in_listed_apps() {
+        let blacklist = [
+            app("/usr/bin/firefox", &[], &[Feature::AutoReplacement]),
+            app("/usr/bin/chromium", &[], &[Feature::AutoReplacement]),
+        ];
+
+        assert!(is_feature_allowed(&blacklist, Feature::AutoReplacement, "/usr/bin/firefox"));
+        assert!(is_feature_allowed(&blacklist, Feature::AutoReplacement, "/usr/bin/chromium"));
+        assert!(!is_feature_allowed(&blacklist, Feature::AutoReplacement, "/usr/bin/editor"));
+        assert!(is_feature_allowed(&blacklist, Feature::Clipboard, "/usr/bin/editor"));
+    }
+
+    #[test]
+    fn ignores_disabled_entries() {
+        let mut only = app("/usr/bin/firefox", &[], &[Feature::AutoReplacement]);
+        only.enabled = false;
+        let mut disabled = app("/usr/bin/game", &[Feature::Clipboard], &[]);
+        disabled.enabled = false;
+        let blacklist = [only, disabled];
+
+        assert!(is_feature_allowed(&blacklist, Feature::AutoReplacement, "/usr/bin/editor"));
+        assert!(is_feature_allowed(&blacklist, Feature::Clipboard, "/usr/bin/game"));
+    }
+
+    #[test]
+    fn allows_everything_with_an_empty_blacklist() {
+        for feature in Feature::ALL {
+            assert!(is_feature_allowed(&[], feature, "/usr/bin/editor"));
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use crate::filesys::{Payload, FOLDER_CLIPBOARD};
+use crate::helpers::{get_tauri_handle, get_timestamp_millis};
+use crate::history::{get_history_instance, save_history, HistoryItem};
+use crate::settings::get_settings_instance;
+use crate::{folders, thumbnails};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use tauri::Manager;
+
+pub static JANITOR_INTERVAL: u64 = 60_000;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RemovedItem {
+    pub folder: String,
+    pub name: String,
+}
+
+struct Rules {
+    max_age_days: HashMap<String, u32>,
+    max_total_bytes: u64,
+    max_item_bytes: u64,
+}
+
+fn rules() -> Rules {
+    let settings = get_settings_instance();
+    let settings = settings.lock();
+
+    Rules {
+        max_age_days: settings.folder_max_age_days.clone(),
+        max_total_bytes: settings.history_max_bytes,
+        max_item_bytes: settings.item_max_bytes,
+    }
+}
+
+/// False if contents are bigger than the per-item size cap
+pub fn fits_item_cap(size: u64) -> bool {
+    let max_item_bytes = rules().max_item_bytes;
+
+    max_item_bytes == 0 || size <= max_item_bytes
+}
+
+pub fn run_janitor() {
+    let _ = thread::Builder::new()
+        .name("history:janitor".to_string())
+        .spawn(|| loop {
+            enforce_and_notify();
+            thumbnails::remove_orphans();
+
+            thread::sleep(Duration::from_millis(JANITOR_INTERVAL));
+        });
+}
+
+/// Applies retention rules and tells UI which items were removed
+pub fn enforce_and_notify() {
+    let removed = enforce();
+    if removed.is_empty() {
+        return;
+    }
+
+    let app = get_tauri_handle();
+    let removed: Vec<RemovedItem> = removed
+        .into_iter()
+        .map(|i| RemovedItem {
+            folder: i.folder,
+            name: i.name,
+        })
+        .collect();
+
+    app.emit_all("clipboard_removed", &removed).unwrap();
+    app.emit_all(
+        "clipboard",
+        Payload {
+            message: String::from("contents"),
+        },
+    )
+    .unwrap();
+}
+
+/// Removes unpinned items older than the max age of their folder, bigger than the per-item cap,
+/// above the max count of a user folder, and then the oldest clipboard items until the whole history fits the byte quota
+pub fn enforce() -> Vec<HistoryItem> {
+    let rules = rules();
+    let now = get_timestamp_millis();
+    // cloned before history is locked, folder commands lock them the other way round
+    let folders = folders::get_folders_instance().lock().clone();
+
+    let history = get_history_instance();
+    let mut history = history.lock();
+
+    let (mut kept, mut removed): (Vec<HistoryItem>, Vec<HistoryItem>) =
+        history.items().into_iter().partition(|item| {
+            if item.pinned {
+                return true;
+            }
+
+            let max_age = rules.max_age_days.get(&item.folder).copied().unwrap_or(0);
+            let too_old = max_age > 0 && item.created_at + max_age as u64 * DAY_MS < now;
+            let too_big = rules.max_item_bytes > 0 && item.size > rules.max_item_bytes;
+
+            !too_old && !too_big
+        });
+
+    for folder in folders.iter().filter(|f| f.max_count > 0) {
+        let mut unpinned: Vec<usize> = (0..kept.len())
+            .filter(|&i| kept[i].folder == folder.name && !kept[i].pinned)
+            .collect();
+        if unpinned.len() <= folder.max_count as usize {
+            continue;
+        }
+
+        // newest first, everything after the limit goes
+        unpinned.sort_by_key(|&i| std::cmp::Reverse(kept[i].created_at));
+        let mut excess = unpinned.split_off(folder.max_count as usize);
+        excess.sort_unstable_by(|a, b| b.cmp(a));
+        for i in excess {
+            removed.push(kept.remove(i));
+        }
+    }
+
+    if rules.max_total_bytes > 0 {
+        let mut total: u64 = kept.iter().map(|i| i.size).sum();
+
+        // favorites are kept on purpose, only the clipboard history is trimmed
+        kept.sort_by_key(|i| (i.folder != FOLDER_CLIPBOARD || i.pinned, i.created_at));
+        while total > rules.max_total_bytes {
+            match kept.first() {
+                Some(item) if item.folder == FOLDER_CLIPBOARD && !item.pinned => {
+                    total -= item.size;
+                    removed.push(kept.remove(0));
+                }
+                _ => break,
+            }
+        }
+    }
+
+    if removed.is_empty() {
+        return removed;
+    }
+
+    history.replace_items(kept);
+    save_history(&history);
+
+    for file in removed.iter().flat_map(|item| item.files()) {
+        if let Err(e) = fs::remove_file(&file) {
+            eprintln!("Error removing file {:?}: {}", file, e);
+        }
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::FileTypes;
+    use crate::filesys::FOLDER_FAVOURITES;
+    use crate::folders::{FolderSettings, SortOrder};
+    use crate::helpers::{test_data_dir, test_items};
+
+    fn store(folder: &str, created_at: u64, size: usize, pinned: bool) -> HistoryItem {
+        let contents = vec![b'x'; size];
+        let mut item = test_items::item(folder, created_at, FileTypes::TXT, &contents);
+        item.pinned = pinned;
+
+        test_items::store(item, &[&contents])
+    }
+
+    fn user_folder(name: &str, max_count: u16) -> FolderSettings {
+        FolderSettings {
+            name: name.to_string(),
+            created_at: 1,
+            max_count,
+            sort: SortOrder::LastUsed,
+        }
+    }
+
+    fn names(items: &[HistoryItem]) -> Vec<String> {
+        let mut names: Vec<String> = items
+            .iter()
+            .map(|i| format!("{}/{}", i.folder, i.name))
+            .collect();
+        names.sort();
+
+        names
+    }
+
+    fn remaining() -> Vec<String> {
+        names(&get_history_instance().lock().items())
+    }
+
+    #[test]
+    fn expires_items_by_folder_age() {
+        let _dir = test_data_dir::new("retention-age");
+        get_settings_instance()
+            .lock()
+            .folder_max_age_days
+            .insert(FOLDER_CLIPBOARD.to_string(), 7);
+
+        let now = get_timestamp_millis();
+        let old = now - 8 * DAY_MS;
+        let expired = store(FOLDER_CLIPBOARD, old, 1, false);
+        store(FOLDER_CLIPBOARD, old + 1, 1, true);
+        store(FOLDER_CLIPBOARD, now - 6 * DAY_MS, 1, false);
+        store(FOLDER_FAVOURITES, old, 1, false);
+
+        let removed = enforce();
+        assert_eq!(names(&removed), [format!("clipboard/{}.txt", old)]);
+        assert!(!expired.path().exists());
+        assert_eq!(remaining().len(), 3);
+
+        assert!(enforce().is_empty());
+    }
+
+    #[test]
+    fn drops_items_above_the_size_cap() {
+        let _dir = test_data_dir::new("retention-size");
+        get_settings_instance().lock().item_max_bytes = 10;
+
+        store(FOLDER_CLIPBOARD, 1, 10, false);
+        store(FOLDER_CLIPBOARD, 2, 11, false);
+        store(FOLDER_FAVOURITES, 3, 11, false);
+        store(FOLDER_FAVOURITES, 4, 11, true);
+
+        let removed = enforce();
+        assert_eq!(names(&removed), ["clipboard/2.txt", "favorites/3.txt"]);
+        assert_eq!(remaining(), ["clipboard/1.txt", "favorites/4.txt"]);
+    }
+
+    #[test]
+    fn trims_oldest_clipboard_items_to_the_quota() {
+        let _dir = test_data_dir::new("retention-quota");
+        get_settings_instance().lock().history_max_bytes = 30;
+
+        store(FOLDER_CLIPBOARD, 1, 10, true);
+        store(FOLDER_CLIPBOARD, 2, 10, false);
+        store(FOLDER_CLIPBOARD, 3, 10, false);
+        store(FOLDER_CLIPBOARD, 4, 10, false);
+        store(FOLDER_FAVOURITES, 0, 10, false);
+
+        let removed = enforce();
+        assert_eq!(names(&removed), ["clipboard/2.txt", "clipboard/3.txt"]);
+        assert_eq!(
+            remaining(),
+            ["clipboard/1.txt", "clipboard/4.txt", "favorites/0.txt"]
+        );
+
+        // pinned items and favorites are kept even above the quota
+        get_settings_instance().lock().history_max_bytes = 5;
+        assert_eq!(names(&enforce()), ["clipboard/4.txt"]);
+        assert_eq!(remaining(), ["clipboard/1.txt", "favorites/0.txt"]);
+    }
+
+    #[test]
+    fn limits_user_folders_to_their_own_count() {
+        let _dir = test_data_dir::new("retention-count");
+        folders::get_folders_instance()
+            .lock()
+            .extend([user_folder("work", 2), user_folder("sql", 0)]);
+
+        for created_at in 1..=4 {
+            store("work", created_at, 1, created_at == 1);
+            store("sql", created_at, 1, false);
+        }
+
+        let removed = enforce();
+        assert_eq!(names(&removed), ["work/2.txt"]);
+        assert_eq!(
+            remaining(),
+            [
+                "sql/1.txt",
+                "sql/2.txt",
+                "sql/3.txt",
+                "sql/4.txt",
+                "work/1.txt",
+                "work/3.txt",
+                "work/4.txt"
+            ]
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use crate::clipboard::my_clipboard::files;
+use crate::clipboard::FileTypes;
+use crate::content_kind::{self, ContentKind};
+use crate::crypto;
+use crate::history::{get_history_instance, HistoryItem};
+use crate::processes::MyProcess;
+use serde::{Deserialize, Serialize};
+
+/// Max chars of an item returned around the first match
+const SNIPPET_LENGTH: usize = 255;
+/// Chars kept before the first match so it's shown in context
+const SNIPPET_CONTEXT: usize = 40;
+/// Fuzzy matching is quadratic, long texts try only the first N positions
+const FUZZY_MAX_STARTS: usize = 64;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    #[default]
+    Substring,
+    Fuzzy,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchQuery {
+    pub query: String,
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Limit search to these folders, all folders if empty
+    #[serde(default)]
+    pub folders: Vec<String>,
+    /// Limit search to these file types (`txt`, `png`, `files`), all types if empty
+    #[serde(default)]
+    pub types: Vec<String>,
+    /// Limit search to items copied from these apps, exe names or paths, all apps if empty
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Limit search to text of these kinds (`url`, `code`, ...), see `ContentKind::name`, all items if empty
+    #[serde(default)]
+    pub kinds: Vec<String>,
+    /// ms since unix epoch, compared to the capture time
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub name: String,
+    pub folder: String,
+    pub path: String,
+    pub extension: String,
+    pub created_at: u64,
+    pub last_used_at: u64,
+    pub source: Option<MyProcess>,
+    pub title: Option<String>,
+    pub kind: Option<ContentKind>,
+    pub score: i64,
+    pub snippet: String,
+    /// [start, end) char ranges of matched parts inside `snippet`
+    pub highlights: Vec<(usize, usize)>,
+}
+
+struct Match {
+    score: i64,
+    /// matched char ranges inside the whole text
+    ranges: Vec<(usize, usize)>,
+}
+
+#[tauri::command]
+pub async fn search_clipboard(query: SearchQuery) -> Result<String, String> {
+    let items = get_history_instance().lock().items();
+
+    let results = search(&query, items);
+
+    serde_json::to_string(&results).map_err(|e| e.to_string())
+}
+
+pub fn search(query: &SearchQuery, items: Vec<HistoryItem>) -> Vec<SearchResult> {
+    let needle: Vec<char> = normalize(&query.query);
+
+    let mut results: Vec<SearchResult> = items
+        .into_iter()
+        .filter(|item| matches_filters(query, item))
+        .filter_map(|item| {
+            let text = searchable_text(&item).unwrap_or_default();
+            let chars: Vec<char> = text.chars().collect();
+
+            let found = if needle.is_empty() {
+                Match {
+                    score: 0,
+                    ranges: vec![],
+                }
+            } else {
+                let haystack = normalize(&text);
+                match query.mode {
+                    SearchMode::Substring => substring_match(&haystack, &needle)?,
+                    SearchMode::Fuzzy => fuzzy_match(&haystack, &needle)?,
+                }
+            };
+
+            let (snippet, highlights) = snippet(&chars, &found.ranges);
+            let kind = content_kind::of_item(&item);
+
+            Some(SearchResult {
+                path: item.path().to_string_lossy().to_string(),
+                extension: item.extension(),
+                name: item.name,
+                folder: item.folder,
+                created_at: item.created_at,
+                last_used_at: item.last_used_at,
+                source: item.source,
+                title: item.title,
+                kind,
+                score: found.score,
+                snippet,
+                highlights,
+            })
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b.last_used_at.cmp(&a.last_used_at))
+    });
+
+    if let Some(limit) = query.limit {
+        results.truncate(limit);
+    }
+
+    results
+}
+
+fn matches_filters(query: &SearchQuery, item: &HistoryItem) -> bool {
+    if !query.folders.is_empty() && !query.folders.contains(&item.folder) {
+        return false;
+    }
+
+    if !query.types.is_empty() && !query.types.contains(&item.extension()) {
+        return false;
+    }
+
+    if !query.sources.is_empty() && !query.sources.iter().any(|s| is_source(item, s)) {
+        return false;
+    }
+
+    if !query.kinds.is_empty()
+        && !content_kind::of_item(item).is_some_and(|k| query.kinds.iter().any(|n| n == k.name()))
+    {
+        return false;
+    }
+
+    if query.from.is_some_and(|from| item.created_at < from) {
+        return false;
+    }
+
+    if query.to.is_some_and(|to| item.created_at > to) {
+        return false;
+    }
+
+    true
+}
+
+/// Source app matches by exe name (`code.exe`) or full path, case doesn't matter
+fn is_source(item: &HistoryItem, source: &str) -> bool {
+    item.source.as_ref().is_some_and(|process| {
+        process.filename.eq_ignore_ascii_case(source)
+            || process.filepath.eq_ignore_ascii_case(source)
+    })
+}
+
+/// Full text of an item, images have none
+fn searchable_text(item: &HistoryItem) -> Option<String> {
+    match item.extension().as_str() {
+        FileTypes::TXT => crypto::read_to_string(&item.path()).ok(),
+        FileTypes::FILES => crypto::read_file(&item.path())
+            .ok()
+            .map(|bytes| files::decode(&bytes).join("\n")),
+        _ => None,
+    }
+}
+
+/// Lowercases keeping exactly one char per source char, so indexes match the original text
+fn normalize(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+fn substring_match(haystack: &[char], needle: &[char]) -> Option<Match> {
+    if needle.len() > haystack.len() {
+        return None;
+    }
+
+    let mut ranges = vec![];
+    let mut i = 0;
+    while i + needle.len() <= haystack.len() {
+        if haystack[i..i + needle.len()] == *needle {
+            ranges.push((i, i + needle.len()));
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+
+    let first = ranges.first()?.0;
+
+    // more occurrences and earlier first occurrence rank higher
+    let score = ranges.len() as i64 * 100 - (first.min(1000) as i64) / 10;
+
+    Some(Match { score, ranges })
+}
+
+/// Matches needle chars in order, rewarding consecutive chars and word starts.
+/// Tries every occurrence of the first char and keeps the best scoring one
+fn fuzzy_match(haystack: &[char], needle: &[char]) -> Option<Match> {
+    let mut best: Option<Match> = None;
+
+    for (start, _) in haystack
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c == needle[0])
+        .take(FUZZY_MAX_STARTS)
+    {
+        let Some(found) = fuzzy_match_from(haystack, needle, start) else {
+            // if needle can't be matched from here it can't be matched from any later position
+            break;
+        };
+
+        if best.as_ref().map_or(true, |b| found.score > b.score) {
+            best = Some(found);
+        }
+    }
+
+    best
+}
+
+fn fuzzy_match_from(haystack: &[char], needle: &[char], start: usize) -> Option<Match> {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    let mut score: i64 = 0;
+    let mut position = start;
+
+    for (n, c) in needle.iter().enumerate() {
+        let index = position + haystack[position..].iter().position(|h| h == c)?;
+
+        score += 10;
+
+        match ranges.last_mut() {
+            Some(last) if last.1 == index => {
+                last.1 = index + 1;
+                score += 15;
+            }
+            _ => {
+                if n > 0 {
+                    // penalize gaps between matched chars
+                    score -= ((index - position) as i64).min(20);
+                }
+                ranges.push((index, index + 1));
+            }
+        }
+
+        if index == 0 || !haystack[index - 1].is_alphanumeric() {
+            score += 10;
+        }
+
+        position = index + 1;
+    }
+
+    Some(Match { score, ranges })
+}
+
+/// Cuts a part of text around the first match and moves highlight ranges into it
+fn snippet(chars: &[char], ranges: &[(usize, usize)]) -> (String, Vec<(usize, usize)>) {
+    let start = ranges
+        .first()
+        .map(|r| r.0.saturating_sub(SNIPPET_CONTEXT))
+        .unwrap_or(0);
+    let end = (start + SNIPPET_LENGTH).min(chars.len());
+
+    let highlights = ranges
+        .iter()
+        .filter(|r| r.0 >= start && r.1 <= end)
+        .map(|r| (r.0 - start, r.1 - start))
+        .collect();
+
+    (chars[start..end].iter().collect(), highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesys::FOLDER_CLIPBOARD;
+    use crate::helpers::test_data_dir;
+    use crate::helpers::test_items::store_file;
+
+    fn query(text: &str) -> SearchQuery {
+        SearchQuery {
+            query: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Items are cloned before searching, kinds are classified and saved while it runs
+    fn results(query: &SearchQuery) -> Vec<SearchResult> {
+        let items = get_history_instance().lock().items();
+
+        search(query, items)
+    }
+
+    fn found(query: &SearchQuery) -> Vec<String> {
+        results(query).into_iter().map(|r| r.name).collect()
+    }
+
+    fn fuzzy(haystack: &str, needle: &str) -> Option<Match> {
+        fuzzy_match(&normalize(haystack), &normalize(needle))
+    }
+
+    #[test]
+    fn ranks_substring_matches_by_count_and_position() {
+        let _dir = test_data_dir::new("search-substring");
+        store_file(FOLDER_CLIPBOARD, 1, "txt", b"Foo bar foo");
+        store_file(FOLDER_CLIPBOARD, 2, "txt", b"bar foo");
+        store_file(FOLDER_CLIPBOARD, 3, "txt", b"f-o-o");
+
+        let results = results(&query("FOO"));
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["1.txt", "2.txt"]);
+        assert_eq!(results[0].highlights, [(0, 3), (8, 11)]);
+        assert_eq!(results[1].highlights, [(4, 7)]);
+        assert!(results[0].score > results[1].score);
+
+        // an empty query lists everything, most recently used first
+        assert_eq!(found(&query("")), ["3.txt", "2.txt", "1.txt"]);
+    }
+
+    #[test]
+    fn fuzzy_matches_chars_in_order() {
+        let _dir = test_data_dir::new("search-fuzzy");
+        store_file(FOLDER_CLIPBOARD, 1, "txt", b"clipboard manager");
+        store_file(FOLDER_CLIPBOARD, 2, "txt", b"drab clip");
+
+        let mut fuzzy_query = query("clpbrd");
+        assert!(found(&fuzzy_query).is_empty());
+
+        fuzzy_query.mode = SearchMode::Fuzzy;
+        assert_eq!(found(&fuzzy_query), ["1.txt"]);
+
+        let found = fuzzy("clipboard manager", "clpbrd").unwrap();
+        assert_eq!(found.ranges, [(0, 2), (3, 5), (7, 9)]);
+    }
+
+    #[test]
+    fn fuzzy_prefers_consecutive_chars_and_word_starts() {
+        let consecutive = fuzzy("hwx", "hw").unwrap();
+        let word_starts = fuzzy("hello world", "hw").unwrap();
+        let inside_words = fuzzy("shower", "hw").unwrap();
+        assert!(consecutive.score > word_starts.score);
+        assert!(word_starts.score > inside_words.score);
+        assert_eq!(word_starts.ranges, [(0, 1), (6, 7)]);
+
+        // the best of all starting positions is kept
+        assert_eq!(fuzzy("ahb hb", "hb").unwrap().ranges, [(4, 6)]);
+
+        assert!(fuzzy("hello", "oh").is_none());
+    }
+
+    #[test]
+    fn cuts_snippets_around_the_first_match() {
+        let text: Vec<char> = format!("{}needle{}", "a".repeat(100), "b".repeat(300))
+            .chars()
+            .collect();
+        let ranges = [(100, 106), (400, 406)];
+
+        let (cut, highlights) = snippet(&text, &ranges);
+        assert_eq!(cut.chars().count(), SNIPPET_LENGTH);
+        assert!(cut.starts_with(&"a".repeat(SNIPPET_CONTEXT)));
+        assert_eq!(&cut[SNIPPET_CONTEXT..SNIPPET_CONTEXT + 6], "needle");
+        // the second match is past the end of the snippet
+        assert_eq!(highlights, [(SNIPPET_CONTEXT, SNIPPET_CONTEXT + 6)]);
+
+        let short: Vec<char> = "short text".chars().collect();
+        assert_eq!(snippet(&short, &[]), ("short text".to_string(), vec![]));
+        assert_eq!(
+            snippet(&short, &[(6, 10)]),
+            ("short text".to_string(), vec![(6, 10)])
+        );
+    }
+
+    #[test]
+    fn filters_by_folder_type_kind_source_and_date() {
+        let _dir = test_data_dir::new("search-filters");
+        store_file(FOLDER_CLIPBOARD, 1, "txt", b"https://example.com");
+        store_file(FOLDER_CLIPBOARD, 2, "txt", b"example text");
+        store_file(FOLDER_CLIPBOARD, 3, "png", b"not really a png");
+        store_file("work", 4, "txt", b"example in a folder");
+        get_history_instance()
+            .lock()
+            .find_mut(FOLDER_CLIPBOARD, "2.txt")
+            .unwrap()
+            .source = Some(MyProcess {
+            pid: 1,
+            title: "Editor".to_string(),
+            filename: "code.exe".to_string(),
+            filepath: "C:\\Apps\\code.exe".to_string(),
+        });
+
+        let mut filtered = query("");
+        filtered.folders = vec!["work".to_string()];
+        assert_eq!(found(&filtered), ["4.txt"]);
+
+        let mut filtered = query("");
+        filtered.types = vec![FileTypes::PNG.to_string()];
+        assert_eq!(found(&filtered), ["3.png"]);
+
+        let mut filtered = query("example");
+        filtered.kinds = vec!["url".to_string()];
+        assert_eq!(found(&filtered), ["1.txt"]);
+
+        let mut filtered = query("example");
+        filtered.sources = vec!["CODE.EXE".to_string()];
+        assert_eq!(found(&filtered), ["2.txt"]);
+        filtered.sources = vec!["c:\\apps\\code.exe".to_string()];
+        assert_eq!(found(&filtered), ["2.txt"]);
+
+        let mut filtered = query("");
+        filtered.from = Some(2);
+        filtered.to = Some(3);
+        assert_eq!(found(&filtered), ["3.png", "2.txt"]);
+
+        let mut limited = query("example");
+        limited.limit = Some(1);
+        assert_eq!(found(&limited).len(), 1);
+    }
+}
@@ -2,13 +2,33 @@ use crate::{
     autorun::autorun,
     filesys::{read_json_data, FILENAME_SETTINGS},
     hotkeys_listener,
+    sensitive::{default_sensitive_policies, SensitiveKind, SensitivePolicy},
 };
 use parking_lot::Mutex;
 use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 use std::sync::{Arc, OnceLock};
 use crate::filesys::write_json_data;
 
 pub static DEFAULT_MAX_CLIPBOARD_ITEMS: u16 = 150;
+pub static DEFAULT_IMAGE_SIMILARITY: u32 = 0;
+pub static DEFAULT_PASTE_RESTORE_DELAY: u64 = 300;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_paste_restore_delay() -> u64 {
+    DEFAULT_PASTE_RESTORE_DELAY
+}
+
+fn default_image_similarity() -> u32 {
+    DEFAULT_IMAGE_SIMILARITY
+}
+
+fn default_paste_queue_hotkey() -> String {
+    "LControl,LAlt,V".to_string()
+}
 
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -19,6 +39,40 @@ pub struct Settings {
     pub win_key_hotkey: String,
     pub win_key_text: String,
     pub show_app_hotkey: String,
+    /// what to do with copied passwords, keys etc., see `sensitive`
+    #[serde(default = "default_sensitive_policies")]
+    pub sensitive_policies: HashMap<SensitiveKind, SensitivePolicy>,
+    /// items older than N days are removed from a folder, 0 or missing folder - never
+    #[serde(default)]
+    pub folder_max_age_days: HashMap<String, u32>,
+    /// total size of all items, 0 - unlimited
+    #[serde(default)]
+    pub history_max_bytes: u64,
+    /// bigger items aren't stored, 0 - unlimited
+    #[serde(default)]
+    pub item_max_bytes: u64,
+    /// images of the same size whose perceptual hashes differ by at most N bits (of 64) are treated as duplicates,
+    /// 0 - identical images only. Off by default: different screenshots of text often differ by a few bits
+    #[serde(default = "default_image_similarity")]
+    pub image_similarity_threshold: u32,
+    /// pastes the next item of paste queue, see `paste_queue`
+    #[serde(default = "default_paste_queue_hotkey")]
+    pub paste_queue_hotkey: String,
+    /// puts back what was on clipboard before a paste from history, otherwise clipboard is cleared
+    #[serde(default = "default_true")]
+    pub restore_clipboard_after_paste: bool,
+    /// time given to the target app to read pasted contents before clipboard is restored
+    #[serde(default = "default_paste_restore_delay")]
+    pub paste_restore_delay_ms: u64,
+    /// stores text highlighted with mouse (X11 PRIMARY selection), Linux only
+    #[serde(default)]
+    pub capture_primary_selection: bool,
+    /// keeps PRIMARY selection and clipboard text the same, Linux only
+    #[serde(default)]
+    pub sync_primary_selection: bool,
+    /// serves copied contents after the source app exits, Linux only
+    #[serde(default = "default_true")]
+    pub keep_clipboard_after_exit: bool,
 }
 
 pub static SETTINGS: OnceLock<Arc<Mutex<Settings>>> = OnceLock::new();
@@ -77,6 +131,17 @@ pub fn get_settings_instance() -> SettingsInstance {
                 win_key_hotkey: "".to_string(),
                 win_key_text: "".to_string(),
                 show_app_hotkey: "LControl,Key1".to_string(),
+                sensitive_policies: default_sensitive_policies(),
+                folder_max_age_days: HashMap::new(),
+                history_max_bytes: 0,
+                item_max_bytes: 0,
+                image_similarity_threshold: DEFAULT_IMAGE_SIMILARITY,
+                paste_queue_hotkey: default_paste_queue_hotkey(),
+                restore_clipboard_after_paste: true,
+                paste_restore_delay_ms: DEFAULT_PASTE_RESTORE_DELAY,
+                capture_primary_selection: false,
+                sync_primary_selection: false,
+                keep_clipboard_after_exit: true,
             }))
         })
         .clone()
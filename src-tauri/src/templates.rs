@@ -0,0 +1,322 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_TIME_FORMAT: &str = "%H:%M:%S";
+
+/// Placeholders of favorites marked as templates, expanded on paste, `{name}` or `{name:argument}`.
+/// Braces not followed by a known name are kept as is, `{{` and `}}` are literal braces
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placeholder {
+    /// `{date}`, `{date:%d.%m.%Y}`
+    Date(String),
+    /// `{time}`, `{time:%H:%M}`
+    Time(String),
+    /// text which was on clipboard before the paste
+    Clipboard,
+    /// random v4 uuid
+    Uuid,
+    /// `{env:USER}`
+    Env(String),
+    /// where the caret is left after the paste
+    Cursor,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+/// Values placeholders are expanded with
+pub struct Context {
+    pub now: DateTime<Local>,
+    pub clipboard: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expanded {
+    pub text: String,
+    /// number of Left arrow presses moving the caret from the end of pasted text to `{cursor}`
+    pub cursor_moves: Option<usize>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut has_cursor = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            match (chars[i], chars.get(i + 1)) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    literal.push(chars[i]);
+                    i += 2;
+                }
+                ('{', _) => match placeholder_at(&chars, i)? {
+                    Some((placeholder, end)) => {
+                        if placeholder == Placeholder::Cursor {
+                            if has_cursor {
+                                return Err(format!(
+                                    "Only one {{cursor}} is allowed, another one is at {}",
+                                    i
+                                ));
+                            }
+                            has_cursor = true;
+                        }
+
+                        if !literal.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut literal)));
+                        }
+                        segments.push(Segment::Placeholder(placeholder));
+                        i = end;
+                    }
+                    None => {
+                        literal.push('{');
+                        i += 1;
+                    }
+                },
+                (c, _) => {
+                    literal.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Text(literal));
+        }
+
+        Ok(Template { segments })
+    }
+
+    /// Text without placeholders is pasted as it's stored, with formatting
+    pub fn has_placeholders(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|s| matches!(s, Segment::Placeholder(_)))
+    }
+
+    pub fn expand(&self, context: &Context) -> Result<Expanded, String> {
+        let mut text = String::new();
+        let mut cursor = None;
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(literal) => text.push_str(literal),
+                Segment::Placeholder(Placeholder::Date(format))
+                | Segment::Placeholder(Placeholder::Time(format)) => text.push_str(
+                    &context
+                        .now
+                        .format_with_items(StrftimeItems::new(format))
+                        .to_string(),
+                ),
+                Segment::Placeholder(Placeholder::Clipboard) => text.push_str(&context.clipboard),
+                Segment::Placeholder(Placeholder::Uuid) => {
+                    text.push_str(&uuid::Uuid::new_v4().to_string())
+                }
+                Segment::Placeholder(Placeholder::Env(name)) => {
+                    let value = std::env::var(name)
+                        .map_err(|_| format!("Environment variable `{}` is not set", name))?;
+                    text.push_str(&value);
+                }
+                Segment::Placeholder(Placeholder::Cursor) => cursor = Some(text.len()),
+            }
+        }
+
+        // editors move over a line break with one press, whatever it's made of
+        let cursor_moves =
+            cursor.map(|position| text[position..].chars().filter(|c| *c != '\r').count());
+
+        Ok(Expanded { text, cursor_moves })
+    }
+}
+
+impl Context {
+    pub fn new(clipboard: String) -> Self {
+        Context {
+            now: Local::now(),
+            clipboard,
+        }
+    }
+}
+
+/// Placeholder starting at `start` and the index right after it, None if it's not a known placeholder
+fn placeholder_at(chars: &[char], start: usize) -> Result<Option<(Placeholder, usize)>, String> {
+    let name: String = chars[start + 1..]
+        .iter()
+        .take_while(|c| c.is_ascii_lowercase())
+        .collect();
+
+    if !matches!(
+        name.as_str(),
+        "date" | "time" | "clipboard" | "uuid" | "env" | "cursor"
+    ) {
+        return Ok(None);
+    }
+
+    let after_name = start + 1 + name.len();
+    let argument = match chars.get(after_name) {
+        Some('}') => None,
+        Some(':') => Some(after_name + 1),
+        // e.g. `{dates}`, not a placeholder
+        _ => return Ok(None),
+    };
+
+    let Some(end) = chars[after_name..].iter().position(|c| *c == '}') else {
+        return Err(format!(
+            "Placeholder `{{{}` at {} is not closed",
+            name, start
+        ));
+    };
+    let end = after_name + end;
+    let argument: Option<String> = argument.map(|from| chars[from..end].iter().collect());
+
+    let placeholder = match (name.as_str(), argument) {
+        ("date", format) => Placeholder::Date(date_format(format, DEFAULT_DATE_FORMAT, start)?),
+        ("time", format) => Placeholder::Time(date_format(format, DEFAULT_TIME_FORMAT, start)?),
+        ("env", Some(variable)) if !variable.is_empty() => Placeholder::Env(variable),
+        ("env", _) => {
+            return Err(format!(
+                "Placeholder `{{env}}` at {} needs a variable name, e.g. {{env:USER}}",
+                start
+            ))
+        }
+        (_, Some(_)) => {
+            return Err(format!(
+                "Placeholder `{{{}}}` at {} takes no argument",
+                name, start
+            ))
+        }
+        ("clipboard", None) => Placeholder::Clipboard,
+        ("uuid", None) => Placeholder::Uuid,
+        ("cursor", None) => Placeholder::Cursor,
+        _ => return Ok(None),
+    };
+
+    Ok(Some((placeholder, end + 1)))
+}
+
+/// Checks strftime format once, so expanding never fails on it.
+/// A format without any field, e.g. ` 5` in `{time: 5}`, is more likely code than a placeholder
+fn date_format(format: Option<String>, default: &str, start: usize) -> Result<String, String> {
+    let format = format.unwrap_or_else(|| default.to_string());
+
+    let items: Vec<Item> = StrftimeItems::new(&format).collect();
+    let has_fields = items
+        .iter()
+        .any(|item| matches!(item, Item::Numeric(..) | Item::Fixed(_)));
+
+    if !has_fields || items.contains(&Item::Error) {
+        return Err(format!("Invalid date format `{}` at {}", format, start));
+    }
+
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn context() -> Context {
+        Context {
+            now: Local.with_ymd_and_hms(2024, 3, 7, 9, 5, 30).unwrap(),
+            clipboard: "copied".to_string(),
+        }
+    }
+
+    fn expand(text: &str) -> Expanded {
+        Template::parse(text).unwrap().expand(&context()).unwrap()
+    }
+
+    #[test]
+    fn keeps_plain_text_and_unknown_braces() {
+        let template = Template::parse("fn main() { let x = {a}; {dates} }").unwrap();
+        assert!(!template.has_placeholders());
+        assert_eq!(
+            expand("fn main() { let x = {a}; {dates} }").text,
+            "fn main() { let x = {a}; {dates} }"
+        );
+    }
+
+    #[test]
+    fn unescapes_double_braces() {
+        assert_eq!(expand("{{date}} is {{}}").text, "{date} is {}");
+    }
+
+    #[test]
+    fn expands_date_and_time() {
+        assert_eq!(expand("{date}").text, "2024-03-07");
+        assert_eq!(expand("{date:%d.%m.%Y}").text, "07.03.2024");
+        assert_eq!(expand("{time}").text, "09:05:30");
+        assert_eq!(expand("at {time:%H:%M}").text, "at 09:05");
+    }
+
+    #[test]
+    fn expands_clipboard_and_env() {
+        std::env::set_var("CBOARD_TEMPLATE_TEST", "value");
+
+        assert_eq!(
+            expand("[{clipboard}] {env:CBOARD_TEMPLATE_TEST}").text,
+            "[copied] value"
+        );
+
+        let missing = Template::parse("{env:CBOARD_TEMPLATE_MISSING}")
+            .unwrap()
+            .expand(&context());
+        assert!(missing.unwrap_err().contains("CBOARD_TEMPLATE_MISSING"));
+    }
+
+    #[test]
+    fn expands_uuid() {
+        let text = expand("{uuid}").text;
+        let uuid = uuid::Uuid::parse_str(&text).unwrap();
+        assert_eq!(uuid.get_version_num(), 4);
+        assert_ne!(expand("{uuid}").text, text);
+    }
+
+    #[test]
+    fn counts_cursor_moves() {
+        let expanded = expand("<b>{cursor}</b>");
+        assert_eq!(expanded.text, "<b></b>");
+        assert_eq!(expanded.cursor_moves, Some(4));
+
+        assert_eq!(expand("a{cursor}\r\nb").cursor_moves, Some(2));
+        assert_eq!(expand("text{cursor}").cursor_moves, Some(0));
+        assert_eq!(expand("text").cursor_moves, None);
+    }
+
+    #[test]
+    fn reports_malformed_placeholders() {
+        let error = |text: &str| Template::parse(text).unwrap_err();
+
+        assert_eq!(
+            error("ab {date:%Y"),
+            "Placeholder `{date` at 3 is not closed"
+        );
+        assert_eq!(
+            error("{env}"),
+            "Placeholder `{env}` at 0 needs a variable name, e.g. {env:USER}"
+        );
+        assert!(error("{env:}").contains("needs a variable name"));
+        assert_eq!(
+            error("{uuid:4}"),
+            "Placeholder `{uuid}` at 0 takes no argument"
+        );
+        assert_eq!(error("{date:%Q}"), "Invalid date format `%Q` at 0");
+        assert!(error("{time:}").starts_with("Invalid date format"));
+        assert_eq!(
+            error("const o = {time: 5}"),
+            "Invalid date format ` 5` at 10"
+        );
+        assert!(error("{cursor} {cursor}").starts_with("Only one {cursor}"));
+    }
+}
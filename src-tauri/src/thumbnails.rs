@@ -0,0 +1,117 @@
+use crate::crypto;
+use crate::filesys::PathBufTauri;
+use crate::helpers::app_data_dir;
+use crate::history::{self, get_history_instance, HistoryItem};
+use crate::phash;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use image::DynamicImage;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+/// Max width and height of a thumbnail, aspect ratio is kept
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// Kept outside of `data`, every subdirectory there is listed as a folder
+pub const FOLDER_THUMBNAILS: &str = "thumbnails";
+
+pub fn dir() -> PathBuf {
+    app_data_dir().join(FOLDER_THUMBNAILS)
+}
+
+/// Thumbnails are named by content hash, so moved and duplicated items share one
+fn path(hash: &str) -> PathBuf {
+    dir().join(format!("{}.png", hash))
+}
+
+pub fn make(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut bytes: Vec<u8> = Vec::new();
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+
+    Ok(bytes)
+}
+
+pub fn save(hash: &str, thumbnail: &[u8]) -> Result<(), String> {
+    fs::create_dir_all(dir()).map_err(|e| e.to_string())?;
+
+    crypto::write_file(&path(hash), thumbnail)
+}
+
+/// Url of the item thumbnail, generated and cached on first request
+pub fn url(item: &HistoryItem) -> Option<String> {
+    let path = path(&item.hash);
+    if !path.is_file() {
+        if let Err(e) = generate(item) {
+            eprintln!("Couldn't create thumbnail of {}: {}", item.name, e);
+            return None;
+        }
+    }
+
+    image_url(path, item.encrypted)
+}
+
+/// Url of a png file, webview can't load encrypted files by path so they're inlined
+pub fn image_url(path: PathBuf, encrypted: bool) -> Option<String> {
+    if encrypted {
+        return crypto::read_file(&path)
+            .ok()
+            .map(|bytes| format!("data:image/png;base64,{}", BASE64.encode(bytes)));
+    }
+
+    Some(path.asset_path())
+}
+
+/// Creates a thumbnail from the stored file, also records dimensions and phash of items captured before they were
+fn generate(item: &HistoryItem) -> Result<(), String> {
+    let bytes = crypto::read_file(&item.path())?;
+    let image = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+
+    save(&item.hash, &make(&image)?)?;
+
+    if item.width.is_none() || item.phash.is_none() {
+        let phash = phash::dhash(&image.to_rgba8());
+
+        history::update_item(&item.folder, &item.name, |stored| {
+            stored.width = Some(image.width());
+            stored.height = Some(image.height());
+            stored.phash = Some(phash);
+        });
+    }
+
+    Ok(())
+}
+
+/// Removes thumbnails of items which are no longer in history
+pub fn remove_orphans() {
+    let hashes: HashSet<String> = get_history_instance()
+        .lock()
+        .items()
+        .into_iter()
+        .map(|i| i.hash)
+        .collect();
+
+    let Ok(entries) = fs::read_dir(dir()) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let hash = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if !hashes.contains(&hash) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Thumbnails are rebuilt lazily, used when hashes or encryption of all items change
+pub fn remove_all() {
+    let _ = fs::remove_dir_all(dir());
+}
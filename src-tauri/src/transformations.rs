@@ -0,0 +1,255 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Deserialize;
+
+/// Unreserved chars of RFC 3986 are left as is
+const URL_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Applied to text before pasting, see `clipboard::paste_transformed`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transformation {
+    /// drops html/rtf, text itself is not changed
+    PlainText,
+    UpperCase,
+    LowerCase,
+    TitleCase,
+    SnakeCase,
+    CamelCase,
+    Trim,
+    CollapseWhitespace,
+    JsonPretty,
+    JsonMinify,
+    UrlEncode,
+    UrlDecode,
+    Base64Encode,
+    Base64Decode,
+    SortLines,
+    UniqueLines,
+}
+
+impl Transformation {
+    pub fn apply(&self, text: &str) -> Result<String, String> {
+        Ok(match self {
+            Transformation::PlainText => text.to_string(),
+            Transformation::UpperCase => text.to_uppercase(),
+            Transformation::LowerCase => text.to_lowercase(),
+            Transformation::TitleCase => title_case(text),
+            Transformation::SnakeCase => map_lines(text, |line| words(line).join("_")),
+            Transformation::CamelCase => map_lines(text, camel_case),
+            Transformation::Trim => text.trim().to_string(),
+            Transformation::CollapseWhitespace => {
+                text.split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+            Transformation::JsonPretty => {
+                serde_json::to_string_pretty(&parse_json(text)?).map_err(|e| e.to_string())?
+            }
+            Transformation::JsonMinify => {
+                serde_json::to_string(&parse_json(text)?).map_err(|e| e.to_string())?
+            }
+            Transformation::UrlEncode => utf8_percent_encode(text, URL_ENCODE_SET).to_string(),
+            Transformation::UrlDecode => percent_decode_str(text)
+                .decode_utf8()
+                .map_err(|e| e.to_string())?
+                .to_string(),
+            Transformation::Base64Encode => BASE64.encode(text),
+            Transformation::Base64Decode => {
+                let bytes = BASE64.decode(text.trim()).map_err(|e| e.to_string())?;
+                String::from_utf8(bytes).map_err(|_| "Decoded data is not text".to_string())?
+            }
+            Transformation::SortLines => {
+                let mut lines: Vec<&str> = text.lines().collect();
+                lines.sort();
+                join_lines(text, lines)
+            }
+            Transformation::UniqueLines => {
+                let mut seen = std::collections::HashSet::new();
+                let lines: Vec<&str> = text.lines().filter(|l| seen.insert(*l)).collect();
+                join_lines(text, lines)
+            }
+        })
+    }
+}
+
+/// Applies transformations one after another
+pub fn apply_all(text: &str, transformations: &[Transformation]) -> Result<String, String> {
+    transformations
+        .iter()
+        .try_fold(text.to_string(), |text, t| t.apply(&text))
+}
+
+fn parse_json(text: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(text).map_err(|e| format!("Not a valid JSON: {}", e))
+}
+
+/// Keeps the trailing newline of the original text
+fn join_lines(original: &str, lines: Vec<&str>) -> String {
+    let mut joined = lines.join("\n");
+    if original.ends_with('\n') {
+        joined.push('\n');
+    }
+
+    joined
+}
+
+fn map_lines(text: &str, f: impl Fn(&str) -> String) -> String {
+    let lines: Vec<String> = text.lines().map(f).collect();
+
+    join_lines(text, lines.iter().map(|l| l.as_str()).collect())
+}
+
+/// Lowercased words of an identifier or a phrase: `fooBar baz-qux` -> `foo`, `bar`, `baz`, `qux`
+fn words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut previous: Option<char> = None;
+
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else {
+            let boundary = c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase());
+            if boundary && !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            current.extend(c.to_lowercase());
+        }
+
+        previous = Some(c);
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(|c| c.to_lowercase()))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+fn camel_case(text: &str) -> String {
+    words(text)
+        .iter()
+        .enumerate()
+        .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+        .collect()
+}
+
+/// Capitalizes every word keeping whitespace as is
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut word = String::new();
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            result.push_str(&capitalize(&word));
+            word.clear();
+            result.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    result.push_str(&capitalize(&word));
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transformation::*;
+    use super::*;
+
+    fn apply(t: Transformation, text: &str) -> String {
+        t.apply(text).unwrap()
+    }
+
+    #[test]
+    fn plain_text_keeps_text() {
+        assert_eq!(apply(PlainText, "Some <b>text</b>"), "Some <b>text</b>");
+    }
+
+    #[test]
+    fn changes_case() {
+        assert_eq!(apply(UpperCase, "Hello, Мир"), "HELLO, МИР");
+        assert_eq!(apply(LowerCase, "Hello, Мир"), "hello, мир");
+        assert_eq!(apply(TitleCase, "hello  wORLD\nfoo"), "Hello  World\nFoo");
+    }
+
+    #[test]
+    fn converts_identifiers() {
+        assert_eq!(apply(SnakeCase, "fooBar baz-qux"), "foo_bar_baz_qux");
+        assert_eq!(
+            apply(SnakeCase, "Hello World\nsomeValue\n"),
+            "hello_world\nsome_value\n"
+        );
+        assert_eq!(apply(CamelCase, "foo_bar baz-qux"), "fooBarBazQux");
+        assert_eq!(apply(CamelCase, "Hello World"), "helloWorld");
+    }
+
+    #[test]
+    fn trims_and_collapses_whitespace() {
+        assert_eq!(apply(Trim, "  \n text here \t\n"), "text here");
+        assert_eq!(apply(CollapseWhitespace, " a  b\t\tc\n\nd "), "a b c d");
+    }
+
+    #[test]
+    fn formats_json() {
+        assert_eq!(
+            apply(JsonMinify, "{ \"b\": 1,\n \"a\": [1, 2] }"),
+            "{\"b\":1,\"a\":[1,2]}"
+        );
+        assert_eq!(apply(JsonPretty, "{\"a\":1}"), "{\n  \"a\": 1\n}");
+        assert!(JsonPretty.apply("{not json").is_err());
+    }
+
+    #[test]
+    fn encodes_and_decodes_url() {
+        assert_eq!(
+            apply(UrlEncode, "a b&c=d/é-_.~"),
+            "a%20b%26c%3Dd%2F%C3%A9-_.~"
+        );
+        assert_eq!(apply(UrlDecode, "a%20b%26c%3Dd%2F%C3%A9"), "a b&c=d/é");
+        assert!(UrlDecode.apply("%FF").is_err());
+    }
+
+    #[test]
+    fn encodes_and_decodes_base64() {
+        assert_eq!(apply(Base64Encode, "hello"), "aGVsbG8=");
+        assert_eq!(apply(Base64Decode, "aGVsbG8=\n"), "hello");
+        assert!(Base64Decode.apply("not base64!").is_err());
+    }
+
+    #[test]
+    fn sorts_and_dedups_lines() {
+        assert_eq!(apply(SortLines, "b\na\nc\n"), "a\nb\nc\n");
+        assert_eq!(apply(UniqueLines, "b\na\nb\na"), "b\na");
+    }
+
+    #[test]
+    fn chains_transformations() {
+        let text = apply_all(
+            "  Hello World  ",
+            &[Trim, SnakeCase, UpperCase, Base64Encode],
+        )
+        .unwrap();
+        assert_eq!(text, BASE64.encode("HELLO_WORLD"));
+
+        assert!(apply_all("{", &[Trim, JsonPretty]).is_err());
+        assert_eq!(apply_all("text", &[]).unwrap(), "text");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::paste_queue::{self, TRAY_ITEM_PASTE_QUEUE};
 use tauri::{AppHandle, CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu};
 
 const STR_TOGGLE: &str = "toggle";
@@ -6,6 +7,7 @@ const STR_QUIT: &str = "quit";
 pub fn make_tray() -> SystemTray {
     let menu = SystemTrayMenu::new()
         .add_item(CustomMenuItem::new(STR_TOGGLE, "Hide"))
+        .add_item(CustomMenuItem::new(TRAY_ITEM_PASTE_QUEUE, "Start paste queue"))
         .add_item(CustomMenuItem::new(STR_QUIT, "Quit"));
 
     SystemTray::new().with_menu(menu)
@@ -38,6 +40,7 @@ pub fn handle_tray_events(app: &AppHandle, event: SystemTrayEvent) {
                 let window = app.get_window("main").unwrap();
                 window.close().unwrap();
             }
+            TRAY_ITEM_PASTE_QUEUE => paste_queue::toggle(),
             STR_TOGGLE => {
                 let window = app.get_window("main").unwrap();
                 let hide_item_handle = app.tray_handle().get_item("toggle");
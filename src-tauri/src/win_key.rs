@@ -1,12 +1,12 @@
 use rdev::{grab, Event, EventType, Key};
-use crate::processes::{app_active_state, is_fg_window_fullscreen};
+use crate::processes::{feature_active, is_fg_window_fullscreen, Feature};
 
 pub async fn watch_win_key() {
     // TODO: read config
 
     let callback = |event: Event| -> Option<Event> {
         if let EventType::KeyPress(Key::MetaLeft) = event.event_type {
-            if app_active_state() && is_fg_window_fullscreen() {
+            if feature_active(Feature::WinKey) && is_fg_window_fullscreen() {
                 None
             } else {
                 Some(event)
@@ -1,6 +1,6 @@
 use crate::hotkeys_listener::parse_keycodes;
 use crate::keys::{hotkeys_list_devicequery_to_rdev, send_hotkeys, send_string};
-use crate::processes::{app_active_state, is_fg_window_fullscreen};
+use crate::processes::{feature_active, is_fg_window_fullscreen, Feature};
 use crate::settings::{get_settings_instance, update_settings, WinKeySetting};
 use std::ptr;
 use winapi::shared::minwindef::{LPARAM, LRESULT, WPARAM};
@@ -11,7 +11,7 @@ use winapi::um::winuser::{
 };
 
 unsafe extern "system" fn hook_callback(code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    if code == HC_ACTION && app_active_state() {
+    if code == HC_ACTION && feature_active(Feature::WinKey) {
         let kb_struct = *(l_param as *const KBDLLHOOKSTRUCT);
 
         if (kb_struct.vkCode == VK_LWIN as u32 || kb_struct.vkCode == VK_RWIN as u32)
@@ -20,7 +20,7 @@ unsafe extern "system" fn hook_callback(code: i32, w_param: WPARAM, l_param: LPA
             let allow_win_key = 0;
             let supress_win_key = 1;
 
-            if !app_active_state() {
+            if !feature_active(Feature::WinKey) {
                 return allow_win_key;
             }
 
@@ -0,0 +1,296 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt, CreateWindowAux, EventMask, Window, WindowClass,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::{COPY_DEPTH_FROM_PARENT, CURRENT_TIME};
+
+/// Selection owners usually answer in a few ms, a hung owner must not block capturing
+const CONVERT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Own connection and hidden window used to request contents of a selection (CLIPBOARD, PRIMARY)
+pub struct X11Selection {
+    conn: RustConnection,
+    window: Window,
+    selection: Atom,
+    property: Atom,
+}
+
+impl X11Selection {
+    pub fn new(selection: &str) -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+        let screen = &conn.setup().roots[screen_num];
+
+        let window = conn.generate_id().map_err(|e| e.to_string())?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            screen.root_visual,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )
+        .map_err(|e| e.to_string())?;
+
+        let selection = intern(&conn, selection)?;
+        let property = intern(&conn, "CBOARD_SELECTION")?;
+
+        Ok(X11Selection {
+            conn,
+            window,
+            selection,
+            property,
+        })
+    }
+
+    pub fn conn(&self) -> &RustConnection {
+        &self.conn
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    pub fn selection(&self) -> Atom {
+        self.selection
+    }
+
+    pub fn atom(&self, name: &str) -> Result<Atom, String> {
+        intern(&self.conn, name)
+    }
+
+    pub fn atom_name(&self, atom: Atom) -> Option<String> {
+        let reply = self.conn.get_atom_name(atom).ok()?.reply().ok()?;
+
+        Some(String::from_utf8_lossy(&reply.name).to_string())
+    }
+
+    /// Names of targets the current owner offers
+    pub fn targets(&self) -> Result<Vec<String>, String> {
+        let reply = self.convert_raw("TARGETS")?;
+
+        Ok(reply
+            .value32()
+            .map(|atoms| atoms.filter_map(|a| self.atom_name(a)).collect())
+            .unwrap_or_default())
+    }
+
+    /// Contents of the selection converted to `target`, e.g. `UTF8_STRING` or `text/html`
+    pub fn convert(&self, target: &str) -> Result<Vec<u8>, String> {
+        Ok(self.convert_raw(target)?.value)
+    }
+
+    /// Property the owner wrote for `target`, with its type and format
+    pub fn convert_raw(
+        &self,
+        target: &str,
+    ) -> Result<x11rb::protocol::xproto::GetPropertyReply, String> {
+        let target = self.atom(target)?;
+
+        self.conn
+            .convert_selection(self.window, self.selection, target, self.property, CURRENT_TIME)
+            .map_err(|e| e.to_string())?;
+        self.conn.flush().map_err(|e| e.to_string())?;
+
+        let deadline = Instant::now() + CONVERT_TIMEOUT;
+        while Instant::now() < deadline {
+            match self.conn.poll_for_event().map_err(|e| e.to_string())? {
+                Some(Event::SelectionNotify(event)) if event.requestor == self.window => {
+                    if event.property == u32::from(AtomEnum::NONE) {
+                        return Err("Selection owner refused the target".to_string());
+                    }
+
+                    // INCR transfers aren't supported, contents requested here are small
+                    return self
+                        .conn
+                        .get_property(true, self.window, self.property, AtomEnum::ANY, 0, u32::MAX / 4)
+                        .map_err(|e| e.to_string())?
+                        .reply()
+                        .map_err(|e| e.to_string());
+                }
+                Some(_) => continue,
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+
+        Err("Selection owner didn't answer in time".to_string())
+    }
+}
+
+impl Drop for X11Selection {
+    fn drop(&mut self) {
+        let _ = self.conn.destroy_window(self.window);
+        let _ = self.conn.flush();
+    }
+}
+
+/// Own connection used to ask the window manager about top level windows (EWMH root properties)
+pub struct X11Windows {
+    conn: RustConnection,
+    root: Window,
+    active_window: Atom,
+    client_list: Atom,
+    wm_pid: Atom,
+    wm_name: Atom,
+    utf8_string: Atom,
+}
+
+impl X11Windows {
+    pub fn new() -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+        let root = conn.setup().roots[screen_num].root;
+
+        Ok(X11Windows {
+            active_window: intern(&conn, "_NET_ACTIVE_WINDOW")?,
+            client_list: intern(&conn, "_NET_CLIENT_LIST")?,
+            wm_pid: intern(&conn, "_NET_WM_PID")?,
+            wm_name: intern(&conn, "_NET_WM_NAME")?,
+            utf8_string: intern(&conn, "UTF8_STRING")?,
+            conn,
+            root,
+        })
+    }
+
+    /// Focused top level window, None when the window manager doesn't tell or nothing is focused
+    pub fn active_window(&self) -> Option<Window> {
+        self.property32(self.root, self.active_window, AtomEnum::WINDOW.into())?
+            .into_iter()
+            .next()
+            .filter(|w| *w != x11rb::NONE)
+    }
+
+    /// Top level windows managed by the window manager
+    pub fn client_list(&self) -> Vec<Window> {
+        self.property32(self.root, self.client_list, AtomEnum::WINDOW.into())
+            .unwrap_or_default()
+    }
+
+    /// Process which created the window, set by the app itself so it may be missing
+    pub fn pid(&self, window: Window) -> Option<u32> {
+        self.property32(window, self.wm_pid, AtomEnum::CARDINAL.into())?
+            .into_iter()
+            .next()
+    }
+
+    /// `_NET_WM_NAME`, or the legacy `WM_NAME` of older apps
+    pub fn title(&self, window: Window) -> String {
+        self.property(window, self.wm_name, self.utf8_string)
+            .or_else(|| self.property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))
+            .map(|name| String::from_utf8_lossy(&name).to_string())
+            .unwrap_or_default()
+    }
+
+    fn property(&self, window: Window, property: Atom, kind: Atom) -> Option<Vec<u8>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, u32::MAX / 4)
+            .ok()?
+            .reply()
+            .ok()?;
+
+        (reply.type_ == kind).then_some(reply.value)
+    }
+
+    fn property32(&self, window: Window, property: Atom, kind: Atom) -> Option<Vec<u32>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, u32::MAX / 4)
+            .ok()?
+            .reply()
+            .ok()?;
+
+        let values = reply.value32()?.collect();
+
+        Some(values)
+    }
+}
+
+pub fn intern(conn: &RustConnection, name: &str) -> Result<Atom, String> {
+    Ok(conn
+        .intern_atom(false, name.as_bytes())
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?
+        .atom)
+}
+
+/// Tests talking to an X server are skipped without one, run them with `xvfb-run cargo test`
+#[cfg(test)]
+pub fn skip_without_display() -> bool {
+    let skip = std::env::var_os("DISPLAY").is_none();
+    if skip {
+        eprintln!("skipped: DISPLAY is not set");
+    }
+
+    skip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::protocol::xproto::PropMode;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    #[test]
+    fn reads_pid_and_title_of_a_window() {
+        if skip_without_display() {
+            return;
+        }
+
+        let app = X11Selection::new("CLIPBOARD").unwrap();
+        let conn = app.conn();
+        let utf8_string = app.atom("UTF8_STRING").unwrap();
+        conn.change_property32(
+            PropMode::REPLACE,
+            app.window(),
+            app.atom("_NET_WM_PID").unwrap(),
+            AtomEnum::CARDINAL,
+            &[std::process::id()],
+        )
+        .unwrap();
+        conn.change_property8(
+            PropMode::REPLACE,
+            app.window(),
+            app.atom("_NET_WM_NAME").unwrap(),
+            utf8_string,
+            "Notes — cboard".as_bytes(),
+        )
+        .unwrap();
+        conn.sync().unwrap();
+
+        let windows = X11Windows::new().unwrap();
+        assert_eq!(windows.pid(app.window()), Some(std::process::id()));
+        assert_eq!(windows.title(app.window()), "Notes — cboard");
+    }
+
+    #[test]
+    fn falls_back_to_legacy_title() {
+        if skip_without_display() {
+            return;
+        }
+
+        let app = X11Selection::new("CLIPBOARD").unwrap();
+        app.conn()
+            .change_property8(
+                PropMode::REPLACE,
+                app.window(),
+                AtomEnum::WM_NAME,
+                AtomEnum::STRING,
+                b"xterm",
+            )
+            .unwrap();
+        app.conn().sync().unwrap();
+
+        let windows = X11Windows::new().unwrap();
+        assert_eq!(windows.pid(app.window()), None);
+        assert_eq!(windows.title(app.window()), "xterm");
+    }
+}
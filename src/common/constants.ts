@@ -3,11 +3,15 @@ export enum Folder {
   Favorites = 1,
 }
 
+/** user-defined folders follow the built-in ones */
+export const USER_FOLDERS_START = 2;
+
 export const FILE_NAME = {
   Autoreplace: "autoreplace.json",
   Blacklist: "blacklist.json",
   Settings: "settings.json",
   KeyboardLayouts: "keyboard_layouts.json",
+  Folders: "folders.json",
 };
 
 export const FOLDER_NAME = {
@@ -31,4 +35,7 @@ export const DIR_DATA = "data";
 export const FILE_EXT = {
   PNG: "png",
   TXT: "txt",
+  HTML: "html",
+  RTF: "rtf",
+  FILES: "files",
 };
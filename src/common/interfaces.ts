@@ -5,12 +5,56 @@ export interface ClipboardItem {
   size: number;
   extension: string;
   contents?: string;
+  files?: FileEntry[] | null;
+  created_at: number; // ms
+  last_used_at: number; // ms
+  hash: string;
+  formats: string[]; // ["text/plain"]
+  source?: ProcessItem | null;
+  paste_count: number;
+  pinned: boolean;
+  encrypted: boolean;
+  expires_at?: number | null; // ms, sensitive items are deleted after it
+  width?: number | null; // original image size, contents hold a thumbnail
+  height?: number | null;
+  title?: string | null; // set by the user, shown above the contents
+  kind?: ContentKind | null; // text items only
+  template?: boolean; // favorites whose placeholders are expanded on paste
+}
+
+export type ContentKind =
+  | { kind: "url" | "email" | "path" | "json" | "number" | "phone" | "text" }
+  | { kind: "color"; hex: string } // "#rrggbb" or "#rrggbbaa"
+  | { kind: "code"; language: string | null };
+
+export interface FileEntry {
+  name: string;
+  path: string;
+  size?: number | null;
+  exists: boolean;
+}
+
+export interface ProcessItem {
+  pid: number;
+  title: string;
+  filename: string;
+  filepath: string;
+}
+
+export type FolderSortOrder = "last_used" | "created_at" | "paste_count";
+
+export interface FolderSettings {
+  name: string;
+  created_at: number;
+  max_count: number; // 0 - unlimited
+  sort: FolderSortOrder;
 }
 
 export interface ClipboardFolder {
   children: [] | ClipboardItem[];
   name: string;
   path: string; // "C:\\Users\\...\\AppData\\Local\\...\\data\\favorites"
+  settings: FolderSettings | null; // null for built-in folders
 }
 
 export type ClipboardData = ClipboardFolder[];
@@ -32,6 +76,13 @@ export interface AppItem {
   title?: string;
 }
 
+export type AppFeature = "clipboard" | "auto_replacement" | "win_key";
+
+export interface BlacklistItem extends AppItem {
+  disabled_features?: AppFeature[];
+  only_features?: AppFeature[];
+}
+
 export interface KeyAppItem extends AppItem {
   lang_id?: number;
 }
@@ -41,3 +92,29 @@ export interface KeyboardLayout {
   lang_code: String;
   lang_name: String;
 }
+
+export interface PasteQueueStatus {
+  enabled: boolean;
+  remaining: number;
+}
+
+export interface EncryptionStatus {
+  enabled: boolean;
+  unlocked: boolean;
+  key_source: "passphrase" | "keyring" | null;
+}
+
+export interface SearchResult {
+  name: string;
+  folder: string;
+  path: string;
+  extension: string;
+  created_at: number; // ms
+  last_used_at: number; // ms
+  source?: ProcessItem | null;
+  title?: string | null;
+  kind?: ContentKind | null;
+  score: number;
+  snippet: string; // part of the text around the first match
+  highlights: [number, number][]; // [start, end) char ranges inside snippet
+}